@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+#[derive(Clone, Debug)]
+pub struct DisjointSet {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+    component_count: usize,
+}
+
+impl DisjointSet {
+    pub fn new(len: usize) -> Self {
+        DisjointSet {
+            parent: (0..len).collect(),
+            size: vec![1; len],
+            component_count: len,
+        }
+    }
+
+    pub fn find(&mut self, item: usize) -> usize {
+        let mut root = item;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        // Path compression
+        let mut current = item;
+        while self.parent[current] != root {
+            let next = self.parent[current];
+            self.parent[current] = root;
+            current = next;
+        }
+        root
+    }
+
+    // Returns false if a and b were already in the same component
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        let (big, small) = if self.size(a) >= self.size(b) {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        self.parent[small] = big;
+        self.size[big] += self.size[small];
+        self.component_count -= 1;
+        true
+    }
+
+    pub fn size(&mut self, item: usize) -> usize {
+        let root = self.find(item);
+        self.size[root]
+    }
+
+    pub fn component_count(&self) -> usize {
+        self.component_count
+    }
+
+    // Components are ordered by their smallest member, members in increasing order
+    pub fn components(&mut self) -> Vec<Vec<usize>> {
+        let mut root_index: HashMap<usize, usize> = HashMap::new();
+        let mut components: Vec<Vec<usize>> = Vec::new();
+        for item in 0..self.parent.len() {
+            let root = self.find(item);
+            let index = *root_index.entry(root).or_insert_with(|| {
+                components.push(Vec::new());
+                components.len() - 1
+            });
+            components[index].push(item);
+        }
+        components
+    }
+
+    pub fn component_sizes(&mut self) -> Vec<usize> {
+        self.components().iter().map(|c| c.len()).collect()
+    }
+}
+
+// Kruskal's algorithm over (weight, a, b) edges, returns the chosen edges in
+// the order they were added. Disconnected graphs give a spanning forest.
+pub fn minimum_spanning_tree<W: Copy + Ord>(
+    node_count: usize,
+    edges: &[(W, usize, usize)],
+) -> Vec<(W, usize, usize)> {
+    let mut sorted = edges.to_vec();
+    sorted.sort_by_key(|(weight, _, _)| *weight);
+    let mut set = DisjointSet::new(node_count);
+    let mut tree = Vec::new();
+    for (weight, a, b) in sorted {
+        if set.component_count() <= 1 {
+            break;
+        }
+        if set.union(a, b) {
+            tree.push((weight, a, b));
+        }
+    }
+    tree
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_union_find() {
+        let mut set = DisjointSet::new(6);
+        assert_eq!(6, set.component_count());
+        assert!(set.union(0, 1));
+        assert!(set.union(2, 3));
+        assert!(set.union(1, 3));
+        assert!(!set.union(0, 2));
+        assert_eq!(set.find(0), set.find(3));
+        assert_ne!(set.find(0), set.find(4));
+        assert_eq!(4, set.size(2));
+        assert_eq!(1, set.size(5));
+        assert_eq!(3, set.component_count());
+        assert_eq!(vec![vec![0, 1, 2, 3], vec![4], vec![5]], set.components());
+        assert_eq!(vec![4, 1, 1], set.component_sizes());
+    }
+
+    #[test]
+    fn test_minimum_spanning_tree() {
+        let edges = vec![
+            (7, 0, 1),
+            (5, 0, 3),
+            (8, 1, 2),
+            (9, 1, 3),
+            (7, 1, 4),
+            (5, 2, 4),
+            (15, 3, 4),
+            (6, 3, 5),
+            (8, 4, 5),
+            (9, 4, 6),
+            (11, 5, 6),
+        ];
+        let tree = minimum_spanning_tree(7, &edges);
+        assert_eq!(6, tree.len());
+        assert_eq!(39, tree.iter().map(|(w, _, _)| w).sum::<i32>());
+        assert_eq!(Some(&(9, 4, 6)), tree.last());
+        // Disconnected graphs produce a forest
+        let forest = minimum_spanning_tree(4, &[(1, 0, 1), (2, 2, 3)]);
+        assert_eq!(2, forest.len());
+    }
+}
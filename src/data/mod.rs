@@ -1,5 +1,9 @@
+mod disjoint_set;
 mod point;
+mod range_set;
 mod string_id_map;
 
+pub use disjoint_set::{minimum_spanning_tree, DisjointSet};
 pub use point::Point2D;
-pub use string_id_map::StringIdMap;
+pub use range_set::RangeSet;
+pub use string_id_map::{IdMap, StringIdMap};
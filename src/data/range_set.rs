@@ -0,0 +1,191 @@
+use std::cmp::Ordering;
+use std::iter::FromIterator;
+use std::ops::{Add, Range, Sub};
+
+// Sorted, non-overlapping, non-adjacent half open ranges
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RangeSet<T> {
+    ranges: Vec<Range<T>>,
+}
+
+impl<T: Copy + Ord> RangeSet<T> {
+    pub fn new() -> Self {
+        RangeSet { ranges: Vec::new() }
+    }
+
+    pub fn insert(&mut self, range: Range<T>) {
+        if range.start >= range.end {
+            return;
+        }
+        let (mut start, mut end) = (range.start, range.end);
+        // Touching ranges get merged, hence < rather than <=
+        let first = self.ranges.partition_point(|r| r.end < start);
+        let mut last = first;
+        while last < self.ranges.len() && self.ranges[last].start <= end {
+            start = start.min(self.ranges[last].start);
+            end = end.max(self.ranges[last].end);
+            last += 1;
+        }
+        self.ranges.splice(first..last, [start..end]);
+    }
+
+    pub fn contains(&self, value: &T) -> bool {
+        let i = self.ranges.partition_point(|r| r.end <= *value);
+        self.ranges.get(i).is_some_and(|r| r.start <= *value)
+    }
+
+    pub fn overlaps(&self, range: &Range<T>) -> bool {
+        if range.start >= range.end {
+            return false;
+        }
+        let i = self.ranges.partition_point(|r| r.end <= range.start);
+        self.ranges.get(i).is_some_and(|r| r.start < range.end)
+    }
+
+    pub fn min(&self) -> Option<T> {
+        self.ranges.first().map(|r| r.start)
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Range<T>> {
+        self.ranges.iter()
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let mut res = self.clone();
+        for range in &other.ranges {
+            res.insert(range.clone());
+        }
+        res
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut res = RangeSet::new();
+        let (mut i, mut j) = (0, 0);
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let (a, b) = (&self.ranges[i], &other.ranges[j]);
+            let start = a.start.max(b.start);
+            let end = a.end.min(b.end);
+            if start < end {
+                res.ranges.push(start..end);
+            }
+            match a.end.cmp(&b.end) {
+                Ordering::Less => i += 1,
+                Ordering::Greater => j += 1,
+                Ordering::Equal => {
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        res
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        match (self.ranges.first(), self.ranges.last()) {
+            (Some(first), Some(last)) => {
+                self.intersection(&other.gaps(first.start..last.end).collect())
+            }
+            _ => RangeSet::new(),
+        }
+    }
+
+    // The uncovered parts of within, in order
+    pub fn gaps(&self, within: Range<T>) -> impl Iterator<Item = Range<T>> + '_ {
+        let end = within.end;
+        let mut cursor = within.start;
+        self.ranges
+            .iter()
+            .map(|r| r.start..r.end)
+            .chain(std::iter::once(end..end))
+            .filter_map(move |r| {
+                let gap = cursor..r.start.min(end);
+                cursor = cursor.max(r.end);
+                (gap.start < gap.end).then_some(gap)
+            })
+    }
+}
+
+impl<T: Copy + Ord + Default + Add<Output = T> + Sub<Output = T>> RangeSet<T> {
+    pub fn len(&self) -> T {
+        self.ranges
+            .iter()
+            .fold(T::default(), |total, r| total + (r.end - r.start))
+    }
+}
+
+impl<T: Copy + Ord> FromIterator<Range<T>> for RangeSet<T> {
+    fn from_iter<I: IntoIterator<Item = Range<T>>>(iter: I) -> Self {
+        let mut set = RangeSet::new();
+        for range in iter {
+            set.insert(range);
+        }
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Spans = [(i32, i32)];
+
+    fn build(ranges: &Spans) -> RangeSet<i32> {
+        ranges.iter().map(|(start, end)| *start..*end).collect()
+    }
+
+    fn ranges(set: &RangeSet<i32>) -> Vec<(i32, i32)> {
+        set.iter().map(|r| (r.start, r.end)).collect()
+    }
+
+    #[test]
+    fn test_insert_merges() {
+        let data: Vec<(&Spans, &Spans)> = vec![
+            (&[(0, 2), (4, 6)], &[(0, 2), (4, 6)]),
+            (&[(4, 6), (0, 2)], &[(0, 2), (4, 6)]),
+            (&[(0, 2), (2, 4)], &[(0, 4)]),
+            (&[(0, 2), (4, 6), (1, 5)], &[(0, 6)]),
+            (&[(0, 2), (4, 6), (8, 9), (-1, 10)], &[(-1, 10)]),
+            (&[(0, 10), (3, 4)], &[(0, 10)]),
+            (&[(0, 2), (5, 5)], &[(0, 2)]),
+        ];
+        for (inserts, expected) in data {
+            assert_eq!(expected, ranges(&build(inserts)));
+        }
+    }
+
+    #[test]
+    fn test_queries() {
+        let set = build(&[(0, 3), (10, 15)]);
+        assert!(set.contains(&0));
+        assert!(set.contains(&2));
+        assert!(!set.contains(&3));
+        assert!(!set.contains(&-1));
+        assert!(set.contains(&14));
+        assert!(!set.contains(&15));
+        assert!(set.overlaps(&(2..5)));
+        assert!(!set.overlaps(&(3..10)));
+        assert!(set.overlaps(&(-5..20)));
+        assert_eq!(8, set.len());
+        assert_eq!(Some(0), set.min());
+        assert_eq!(
+            vec![-2..0, 3..10, 15..20],
+            set.gaps(-2..20).collect::<Vec<_>>()
+        );
+        assert_eq!(vec![4..10], set.gaps(4..12).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a = build(&[(0, 5), (10, 15)]);
+        let b = build(&[(3, 12), (14, 20)]);
+        assert_eq!(vec![(0, 20)], ranges(&a.union(&b)));
+        assert_eq!(
+            vec![(3, 5), (10, 12), (14, 15)],
+            ranges(&a.intersection(&b))
+        );
+        assert_eq!(vec![(0, 3), (12, 14)], ranges(&a.difference(&b)));
+        assert_eq!(vec![(5, 10), (15, 20)], ranges(&b.difference(&a)));
+        assert_eq!(a, a.difference(&RangeSet::new()));
+        assert!(RangeSet::new().difference(&a).iter().next().is_none());
+    }
+}
@@ -1,25 +1,110 @@
+use std::borrow::Borrow;
 use std::collections::HashMap;
+use std::hash::Hash;
 
-#[derive(Debug, Default)]
-pub struct StringIdMap {
-    map: HashMap<String, usize>,
-    next_id: usize,
+use serde::ser::{Serialize, SerializeSeq, Serializer};
+
+// Hands out sequential ids for keys, and can turn those ids back into keys
+#[derive(Clone, Debug)]
+pub struct IdMap<K> {
+    map: HashMap<K, usize>,
+    keys: Vec<K>,
+}
+
+pub type StringIdMap = IdMap<String>;
+
+impl<K> Default for IdMap<K> {
+    fn default() -> Self {
+        IdMap {
+            map: HashMap::new(),
+            keys: Vec::new(),
+        }
+    }
 }
 
-impl StringIdMap {
-    pub fn to_id(&mut self, value: &str) -> usize {
+impl<K: Clone + Eq + Hash> IdMap<K> {
+    pub fn to_id<Q>(&mut self, value: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ToOwned<Owned = K> + ?Sized,
+    {
         match self.map.get(value) {
             Some(id) => *id,
             None => {
-                let id = self.next_id;
-                self.next_id += 1;
-                self.map.insert(value.to_string(), id);
+                let id = self.keys.len();
+                let key = value.to_owned();
+                self.map.insert(key.clone(), id);
+                self.keys.push(key);
                 id
             }
         }
     }
 
+    pub fn get<Q>(&self, value: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.map.get(value).copied()
+    }
+}
+
+impl<K> IdMap<K> {
+    pub fn name(&self, id: usize) -> Option<&K> {
+        self.keys.get(id)
+    }
+
     pub fn count(&self) -> usize {
-        self.next_id
+        self.keys.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &K)> {
+        self.keys.iter().enumerate()
+    }
+}
+
+// Serialized as the list of keys, so a key's index is its id
+impl<K: Serialize> Serialize for IdMap<K> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.count()))?;
+        for (_, key) in self.iter() {
+            seq.serialize_element(key)?;
+        }
+        seq.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_ids() {
+        let mut map = StringIdMap::default();
+        assert_eq!(0, map.to_id("b"));
+        assert_eq!(1, map.to_id("a"));
+        assert_eq!(0, map.to_id("b"));
+        assert_eq!(Some(1), map.get("a"));
+        assert_eq!(None, map.get("c"));
+        assert_eq!(2, map.count());
+        assert_eq!(Some(&"a".to_string()), map.name(1));
+        assert_eq!(None, map.name(2));
+        assert_eq!(
+            vec![(0, "b"), (1, "a")],
+            map.iter()
+                .map(|(id, name)| (id, name.as_str()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(r#"["b","a"]"#, serde_json::to_string(&map).unwrap());
+    }
+
+    #[test]
+    fn test_generic_ids() {
+        let mut map: IdMap<(i32, i32)> = IdMap::default();
+        assert_eq!(0, map.to_id(&(3, 4)));
+        assert_eq!(1, map.to_id(&(1, 2)));
+        assert_eq!(0, map.to_id(&(3, 4)));
+        assert_eq!(Some(&(1, 2)), map.name(1));
+        assert_eq!("[[3,4],[1,2]]", serde_json::to_string(&map).unwrap());
     }
 }
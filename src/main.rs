@@ -26,6 +26,7 @@ use crate::year_2016::{
     Day05 as y2016_d05, Day06 as y2016_d06, Day07 as y2016_d07, Day08 as y2016_d08,
     Day09 as y2016_d09, Day10 as y2016_d10, Day11 as y2016_d11, Day12 as y2016_d12,
     Day13 as y2016_d13, Day14 as y2016_d14, Day15 as y2016_d15, Day16 as y2016_d16,
+    Day23 as y2016_d23, Day25 as y2016_d25,
 };
 use crate::year_2023::{
     Day01 as y2023_d01, Day02 as y2023_d02, Day03 as y2023_d03, Day04 as y2023_d04,
@@ -91,9 +92,9 @@ fn main() -> Result<()> {
             // "2016-20" => y2016_d20::main()?,
             // "2016-21" => y2016_d21::main()?,
             // "2016-22" => y2016_d22::main()?,
-            // "2016-23" => y2016_d23::main()?,
+            "2016-23" => y2016_d23::main()?,
             // "2016-24" => y2016_d24::main()?,
-            // "2016-25" => y2016_d25::main()?,
+            "2016-25" => y2016_d25::main()?,
             "2023-01" => y2023_d01::main()?,
             "2023-02" => y2023_d02::main()?,
             "2023-03" => y2023_d03::main()?,
@@ -1,10 +1,29 @@
-#[derive(Copy, Clone)]
+use std::ops::Mul;
+
+use anyhow::{bail, Context, Error, Result};
+
+use crate::data::Point2D;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Turn {
     Right,
     Left,
+    Around,
+}
+
+impl TryFrom<char> for Turn {
+    type Error = Error;
+
+    fn try_from(ch: char) -> Result<Self> {
+        match ch {
+            'R' => Ok(Turn::Right),
+            'L' => Ok(Turn::Left),
+            _ => bail!("Unexpected turn char {ch}"),
+        }
+    }
 }
 
-#[derive(Copy, Clone, Eq, Hash, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Cardinal {
     North,
     East,
@@ -12,17 +31,238 @@ pub enum Cardinal {
     West,
 }
 
+// Unit vectors use screen coordinates, North is toward y - 1
 impl Cardinal {
+    pub const ALL: [Cardinal; 4] = [
+        Cardinal::North,
+        Cardinal::East,
+        Cardinal::South,
+        Cardinal::West,
+    ];
+
     pub fn turn(self, turn: Turn) -> Self {
         match (self, turn) {
             (Cardinal::North, Turn::Right) => Cardinal::East,
             (Cardinal::North, Turn::Left) => Cardinal::West,
+            (Cardinal::North, Turn::Around) => Cardinal::South,
             (Cardinal::East, Turn::Right) => Cardinal::South,
             (Cardinal::East, Turn::Left) => Cardinal::North,
+            (Cardinal::East, Turn::Around) => Cardinal::West,
             (Cardinal::South, Turn::Right) => Cardinal::West,
             (Cardinal::South, Turn::Left) => Cardinal::East,
+            (Cardinal::South, Turn::Around) => Cardinal::North,
             (Cardinal::West, Turn::Right) => Cardinal::North,
             (Cardinal::West, Turn::Left) => Cardinal::South,
+            (Cardinal::West, Turn::Around) => Cardinal::East,
+        }
+    }
+
+    pub fn unit<T: From<i8>>(self) -> (T, T) {
+        Direction::from(self).unit()
+    }
+
+    pub fn offset<T: From<i8> + Mul<Output = T> + Copy>(self, magnitude: T) -> (T, T) {
+        Direction::from(self).offset(magnitude)
+    }
+}
+
+// Accepts arrows (^>v<), compass letters (NESW) and relative letters (UDLR)
+impl TryFrom<char> for Cardinal {
+    type Error = Error;
+
+    fn try_from(ch: char) -> Result<Self> {
+        match ch {
+            '^' | 'N' | 'U' => Ok(Cardinal::North),
+            '>' | 'E' | 'R' => Ok(Cardinal::East),
+            'v' | 'S' | 'D' => Ok(Cardinal::South),
+            '<' | 'W' | 'L' => Ok(Cardinal::West),
+            _ => bail!("Unexpected direction char {ch}"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::NorthEast,
+        Direction::East,
+        Direction::SouthEast,
+        Direction::South,
+        Direction::SouthWest,
+        Direction::West,
+        Direction::NorthWest,
+    ];
+
+    pub fn turn(self, turn: Turn) -> Self {
+        let index = Direction::ALL
+            .iter()
+            .position(|d| *d == self)
+            .expect("every direction is in ALL");
+        let shift = match turn {
+            Turn::Right => 2,
+            Turn::Left => 6,
+            Turn::Around => 4,
+        };
+        Direction::ALL[(index + shift) % 8]
+    }
+
+    pub fn unit<T: From<i8>>(self) -> (T, T) {
+        let (x, y): (i8, i8) = match self {
+            Direction::North => (0, -1),
+            Direction::NorthEast => (1, -1),
+            Direction::East => (1, 0),
+            Direction::SouthEast => (1, 1),
+            Direction::South => (0, 1),
+            Direction::SouthWest => (-1, 1),
+            Direction::West => (-1, 0),
+            Direction::NorthWest => (-1, -1),
+        };
+        (x.into(), y.into())
+    }
+
+    pub fn offset<T: From<i8> + Mul<Output = T> + Copy>(self, magnitude: T) -> (T, T) {
+        let (x, y): (T, T) = self.unit();
+        (x * magnitude, y * magnitude)
+    }
+}
+
+impl From<Cardinal> for Direction {
+    fn from(cardinal: Cardinal) -> Self {
+        match cardinal {
+            Cardinal::North => Direction::North,
+            Cardinal::East => Direction::East,
+            Cardinal::South => Direction::South,
+            Cardinal::West => Direction::West,
         }
     }
 }
+
+impl TryFrom<Direction> for Cardinal {
+    type Error = Error;
+
+    fn try_from(direction: Direction) -> Result<Self> {
+        Cardinal::ALL
+            .into_iter()
+            .find(|c| Direction::from(*c) == direction)
+            .with_context(|| format!("{direction:?} is not a cardinal direction"))
+    }
+}
+
+impl Point2D<usize> {
+    pub fn step<D: Into<Direction>>(&self, dir: D, mag: usize) -> Option<Self> {
+        let dir = dir.into();
+        let (dx, dy): (i8, i8) = dir.unit();
+        let x = match dx {
+            -1 => self.x.checked_sub(mag)?,
+            1 => self.x.checked_add(mag)?,
+            _ => self.x,
+        };
+        let y = match dy {
+            -1 => self.y.checked_sub(mag)?,
+            1 => self.y.checked_add(mag)?,
+            _ => self.y,
+        };
+        Some(Point2D::new(x, y))
+    }
+
+    // Bounds are inclusive, matching a grid whose bottom right corner is max
+    pub fn step_within<D: Into<Direction>>(
+        &self,
+        dir: D,
+        mag: usize,
+        max: &Point2D<usize>,
+    ) -> Option<Self> {
+        self.step(dir, mag)
+            .filter(|next| next.x <= max.x && next.y <= max.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_turn() {
+        assert_eq!(Cardinal::East, Cardinal::North.turn(Turn::Right));
+        assert_eq!(Cardinal::West, Cardinal::North.turn(Turn::Left));
+        assert_eq!(Cardinal::South, Cardinal::North.turn(Turn::Around));
+        assert_eq!(Direction::SouthEast, Direction::NorthEast.turn(Turn::Right));
+        assert_eq!(Direction::NorthWest, Direction::NorthEast.turn(Turn::Left));
+        assert_eq!(
+            Direction::SouthWest,
+            Direction::NorthEast.turn(Turn::Around)
+        );
+        for dir in Cardinal::ALL {
+            assert_eq!(
+                Direction::from(dir.turn(Turn::Right)),
+                Direction::from(dir).turn(Turn::Right)
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        for (chars, expected) in [
+            ("^NU", Cardinal::North),
+            (">ER", Cardinal::East),
+            ("vSD", Cardinal::South),
+            ("<WL", Cardinal::West),
+        ] {
+            for ch in chars.chars() {
+                assert_eq!(expected, Cardinal::try_from(ch).unwrap());
+            }
+        }
+        assert!(Cardinal::try_from('x').is_err());
+        assert_eq!(Turn::Left, Turn::try_from('L').unwrap());
+        assert_eq!(Turn::Right, Turn::try_from('R').unwrap());
+        assert!(Turn::try_from('U').is_err());
+        assert!(Turn::try_from('B').is_err());
+    }
+
+    #[test]
+    fn test_vectors() {
+        assert_eq!((0, -1), Cardinal::North.unit::<i32>());
+        assert_eq!((-3, 0), Cardinal::West.offset(3i64));
+        assert_eq!((2, 2), Direction::SouthEast.offset(2i32));
+        let sum = Direction::ALL
+            .iter()
+            .map(|d| d.unit::<i32>())
+            .fold((0, 0), |acc, v| (acc.0 + v.0, acc.1 + v.1));
+        assert_eq!((0, 0), sum);
+        assert_eq!(
+            4,
+            Direction::ALL
+                .iter()
+                .filter(|d| Cardinal::try_from(**d).is_ok())
+                .count()
+        );
+    }
+
+    #[test]
+    fn test_step() {
+        let max = Point2D::new(2, 2);
+        let origin: Point2D<usize> = Point2D::new(0, 0);
+        assert_eq!(None, origin.step(Cardinal::North, 1));
+        assert_eq!(
+            Some(Point2D::new(1, 1)),
+            origin.step(Direction::SouthEast, 1)
+        );
+        assert_eq!(None, origin.step_within(Direction::SouthEast, 3, &max));
+        assert_eq!(
+            Some(Point2D::new(0, 2)),
+            Point2D::new(2, 0).step_within(Direction::SouthWest, 2, &max)
+        );
+    }
+}
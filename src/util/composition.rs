@@ -0,0 +1,234 @@
+// A per unit value that has to add up to exactly total, like a cookie's calories
+#[derive(Copy, Clone, Debug)]
+pub struct Exact<'a> {
+    pub per_unit: &'a [i64],
+    pub total: i64,
+}
+
+// The product of the summed properties, 0 if any of them isn't positive
+pub fn score(parts: &[Vec<i64>], amounts: &[u32]) -> i64 {
+    let properties = parts.first().map_or(0, |p| p.len());
+    (0..properties)
+        .map(|p| {
+            parts
+                .iter()
+                .zip(amounts)
+                .map(|(part, amount)| part[p] * i64::from(*amount))
+                .sum::<i64>()
+                .max(0)
+        })
+        .product()
+}
+
+struct Search<'a> {
+    parts: &'a [Vec<i64>],
+    exact: Option<Exact<'a>>,
+    // Highest value of each property among the parts from each index on
+    best_after: Vec<Vec<i64>>,
+    // Lowest and highest exact per unit value among the parts from each index on
+    exact_after: Vec<(i64, i64)>,
+    amounts: Vec<u32>,
+    sums: Vec<i64>,
+    exact_sum: i64,
+    best: Option<(i64, Vec<u32>)>,
+}
+
+impl Search<'_> {
+    // Whether the parts from index on could still beat the best, given left units for them
+    fn promising(&self, index: usize, left: u32) -> bool {
+        let left = i64::from(left);
+        if let Some(exact) = self.exact {
+            let (low, high) = self.exact_after[index];
+            if exact.total < self.exact_sum + left * low
+                || exact.total > self.exact_sum + left * high
+            {
+                return false;
+            }
+        }
+        let mut bound: i64 = 1;
+        for (sum, best) in self.sums.iter().zip(&self.best_after[index]) {
+            let highest = sum + left * best;
+            // A property that can't become positive zeroes the score
+            if highest <= 0 {
+                return false;
+            }
+            bound = bound.saturating_mul(highest);
+        }
+        self.best.as_ref().is_none_or(|(best, _)| bound > *best)
+    }
+
+    // Negative amounts take units back out
+    fn add(&mut self, index: usize, amount: i64) {
+        for (sum, value) in self.sums.iter_mut().zip(&self.parts[index]) {
+            *sum += value * amount;
+        }
+        if let Some(exact) = self.exact {
+            self.exact_sum += exact.per_unit[index] * amount;
+        }
+    }
+
+    fn search(&mut self, index: usize, left: u32) {
+        if index + 1 == self.parts.len() {
+            // The last part takes whatever is left
+            self.add(index, left.into());
+            self.amounts.push(left);
+            let exact = self.exact.is_none_or(|e| e.total == self.exact_sum);
+            let score: i64 = self.sums.iter().map(|s| (*s).max(0)).product();
+            if exact && score > 0 && self.best.as_ref().is_none_or(|(best, _)| score > *best) {
+                self.best = Some((score, self.amounts.clone()));
+            }
+            self.amounts.pop();
+            self.add(index, -i64::from(left));
+            return;
+        }
+        for amount in 0..=left {
+            self.add(index, amount.into());
+            self.amounts.push(amount);
+            if self.promising(index + 1, left - amount) {
+                self.search(index + 1, left - amount);
+            }
+            self.amounts.pop();
+            self.add(index, -i64::from(amount));
+        }
+    }
+}
+
+// Splits total units between the parts, where each part gives every property per unit, to
+// get the highest score. None if no split scores above 0 (or meets the exact constraint).
+pub fn best_composition(
+    parts: &[Vec<i64>],
+    total: u32,
+    exact: Option<Exact>,
+) -> Option<(i64, Vec<u32>)> {
+    let properties = parts.first()?.len();
+    if parts.iter().any(|p| p.len() != properties)
+        || exact.is_some_and(|e| e.per_unit.len() != parts.len())
+    {
+        return None;
+    }
+    let mut best_after = vec![vec![i64::MIN; properties]; parts.len() + 1];
+    let mut exact_after = vec![(i64::MAX, i64::MIN); parts.len() + 1];
+    for index in (0..parts.len()).rev() {
+        for p in 0..properties {
+            best_after[index][p] = best_after[index + 1][p].max(parts[index][p]);
+        }
+        if let Some(exact) = exact {
+            let (low, high) = exact_after[index + 1];
+            let value = exact.per_unit[index];
+            exact_after[index] = (low.min(value), high.max(value));
+        }
+    }
+    let mut search = Search {
+        parts,
+        exact,
+        best_after,
+        exact_after,
+        amounts: Vec::with_capacity(parts.len()),
+        sums: vec![0; properties],
+        exact_sum: 0,
+        best: None,
+    };
+    if search.promising(0, total) {
+        search.search(0, total);
+    }
+    search.best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::random::generator;
+
+    // Tries every split, for checking the search against
+    fn brute_force(parts: &[Vec<i64>], total: u32, exact: Option<Exact>) -> Option<i64> {
+        fn splits(count: usize, total: u32) -> Vec<Vec<u32>> {
+            if count == 1 {
+                return vec![vec![total]];
+            }
+            (0..=total)
+                .flat_map(|amount| {
+                    splits(count - 1, total - amount)
+                        .into_iter()
+                        .map(move |mut rest| {
+                            rest.insert(0, amount);
+                            rest
+                        })
+                })
+                .collect()
+        }
+        splits(parts.len(), total)
+            .into_iter()
+            .filter(|amounts| {
+                exact.is_none_or(|e| {
+                    e.per_unit
+                        .iter()
+                        .zip(amounts)
+                        .map(|(v, a)| v * i64::from(*a))
+                        .sum::<i64>()
+                        == e.total
+                })
+            })
+            .map(|amounts| score(parts, &amounts))
+            .filter(|score| *score > 0)
+            .max()
+    }
+
+    #[test]
+    fn test_example_cookies() {
+        let parts = vec![vec![-1, -2, 6, 3], vec![2, 3, -2, -1]];
+        assert_eq!(
+            Some((62842880, vec![44, 56])),
+            best_composition(&parts, 100, None)
+        );
+        let calories = Exact {
+            per_unit: &[8, 3],
+            total: 500,
+        };
+        assert_eq!(
+            Some((57600000, vec![40, 60])),
+            best_composition(&parts, 100, Some(calories))
+        );
+        assert_eq!(57600000, score(&parts, &[40, 60]));
+        // Nothing scores when a property can't be positive
+        assert_eq!(None, best_composition(&[vec![1, -1], vec![2, 0]], 10, None));
+        let impossible = Exact {
+            per_unit: &[8, 3],
+            total: 1,
+        };
+        assert_eq!(None, best_composition(&parts, 100, Some(impossible)));
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        // Small pseudo random recipes
+        let mut random = generator(7);
+        let mut next = || (random() % 13) as i64 - 5;
+        for count in 1..=4 {
+            for _ in 0..20 {
+                let parts: Vec<Vec<i64>> = (0..count)
+                    .map(|_| (0..3).map(|_| next()).collect())
+                    .collect();
+                let per_unit: Vec<i64> = (0..count).map(|_| next().abs() + 1).collect();
+                let total = 12;
+                let target = per_unit.iter().sum::<i64>() * 3;
+                for exact in [
+                    None,
+                    Some(Exact {
+                        per_unit: &per_unit,
+                        total: target,
+                    }),
+                ] {
+                    let best = best_composition(&parts, total, exact);
+                    assert_eq!(
+                        brute_force(&parts, total, exact),
+                        best.as_ref().map(|(score, _)| *score)
+                    );
+                    if let Some((best_score, amounts)) = best {
+                        assert_eq!(total, amounts.iter().sum::<u32>());
+                        assert_eq!(best_score, score(&parts, &amounts));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,482 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use anyhow::{bail, Context, Result};
+
+// Holds a ';' separated script of debugger commands, or '-' to read commands from stdin
+pub const DEBUG_VAR: &str = "AOC_DEBUG";
+
+pub fn debug_script() -> Option<String> {
+    env::var(DEBUG_VAR).ok()
+}
+
+// An instruction as text, with the absolute index of its jump target (if it's a constant jump)
+pub struct Decoded {
+    pub text: String,
+    pub jump: Option<i64>,
+}
+
+pub trait Debuggable {
+    // None once the machine has halted
+    fn pc(&self) -> Option<usize>;
+    // Returns false if the machine was already halted
+    fn step(&mut self) -> Result<bool>;
+    fn program_len(&self) -> usize;
+    fn register_names(&self) -> Vec<char>;
+    fn register(&self, name: char) -> Option<i64>;
+    fn set_register(&mut self, name: char, value: i64) -> Result<()>;
+    fn decode(&self, index: usize) -> Option<Decoded>;
+}
+
+// Listing of the program, jump targets get labels and the given row prefix
+fn listing<M: Debuggable>(machine: &M, prefix: impl Fn(usize) -> String) -> String {
+    let len = machine.program_len();
+    let decoded: Vec<(usize, Decoded)> = (0..len)
+        .filter_map(|i| Some((i, machine.decode(i)?)))
+        .collect();
+    let targets: BTreeSet<usize> = decoded
+        .iter()
+        .filter_map(|(_, d)| d.jump)
+        .filter_map(|target| usize::try_from(target).ok())
+        .filter(|target| *target < len)
+        .collect();
+    let labels: BTreeMap<usize, String> = targets
+        .into_iter()
+        .enumerate()
+        .map(|(n, target)| (target, format!("L{}", n + 1)))
+        .collect();
+    let mut out = String::new();
+    for (index, instruction) in decoded {
+        let label = labels
+            .get(&index)
+            .map(|l| format!("{l}:"))
+            .unwrap_or_default();
+        let text = match instruction.jump {
+            Some(target) => {
+                let destination = usize::try_from(target)
+                    .ok()
+                    .and_then(|t| labels.get(&t))
+                    .map_or("halt", |l| l.as_str());
+                format!("{} {destination}", instruction.text)
+            }
+            None => instruction.text.clone(),
+        };
+        out.push_str(&format!("{}{label:<5}{index:>4}  {text}\n", prefix(index)));
+    }
+    out
+}
+
+pub fn disassemble<M: Debuggable>(machine: &M) -> String {
+    listing(machine, |_| String::new())
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Stop {
+    Halted,
+    Breakpoint(usize),
+    Watch { register: char, old: i64, new: i64 },
+    StepLimit,
+}
+
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Stop::Halted => write!(f, "halted"),
+            Stop::Breakpoint(index) => write!(f, "breakpoint at {index}"),
+            Stop::Watch { register, old, new } => write!(f, "{register} changed {old} -> {new}"),
+            Stop::StepLimit => write!(f, "step limit"),
+        }
+    }
+}
+
+pub struct Debugger<M, W> {
+    pub machine: M,
+    out: W,
+    breakpoints: BTreeSet<usize>,
+    watches: BTreeSet<char>,
+    // Execution counts per instruction index
+    heat: Vec<usize>,
+    steps: usize,
+    trace: bool,
+}
+
+impl<M: Debuggable, W: Write> Debugger<M, W> {
+    pub fn new(machine: M, out: W) -> Self {
+        let heat = vec![0; machine.program_len()];
+        Debugger {
+            machine,
+            out,
+            breakpoints: BTreeSet::new(),
+            watches: BTreeSet::new(),
+            heat,
+            steps: 0,
+            trace: false,
+        }
+    }
+
+    pub fn set_breakpoint(&mut self, index: usize) -> Result<()> {
+        if index >= self.machine.program_len() {
+            bail!("No instruction at {index}");
+        }
+        self.breakpoints.insert(index);
+        Ok(())
+    }
+
+    pub fn watch(&mut self, register: char) -> Result<()> {
+        if self.machine.register(register).is_none() {
+            bail!("No register '{register}'");
+        }
+        self.watches.insert(register);
+        Ok(())
+    }
+
+    fn registers(&self) -> String {
+        self.machine
+            .register_names()
+            .into_iter()
+            .filter_map(|name| Some(format!("{name}={}", self.machine.register(name)?)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    // Executes one instruction, reporting anything that should interrupt a resume
+    pub fn step(&mut self) -> Result<Option<Stop>> {
+        let Some(pc) = self.machine.pc() else {
+            return Ok(Some(Stop::Halted));
+        };
+        if self.trace {
+            let text = self.machine.decode(pc).map(|d| d.text).unwrap_or_default();
+            let registers = self.registers();
+            writeln!(self.out, "{:>8} {pc:>4}  {text:<16}{registers}", self.steps)?;
+        }
+        let before: Vec<(char, i64)> = self
+            .watches
+            .iter()
+            .filter_map(|r| Some((*r, self.machine.register(*r)?)))
+            .collect();
+        self.machine.step()?;
+        self.steps += 1;
+        if let Some(count) = self.heat.get_mut(pc) {
+            *count += 1;
+        }
+        for (register, old) in before {
+            let new = self.machine.register(register).unwrap_or(old);
+            if new != old {
+                return Ok(Some(Stop::Watch { register, old, new }));
+            }
+        }
+        Ok(match self.machine.pc() {
+            None => Some(Stop::Halted),
+            Some(pc) if self.breakpoints.contains(&pc) => Some(Stop::Breakpoint(pc)),
+            Some(_) => None,
+        })
+    }
+
+    pub fn resume(&mut self, step_limit: Option<usize>) -> Result<Stop> {
+        let mut taken = 0;
+        loop {
+            if step_limit.is_some_and(|limit| taken >= limit) {
+                return Ok(Stop::StepLimit);
+            }
+            if let Some(stop) = self.step()? {
+                return Ok(stop);
+            }
+            taken += 1;
+        }
+    }
+
+    fn heat_listing(&self) -> String {
+        let max = self.heat.iter().copied().max().unwrap_or(0).max(1);
+        let width = 20;
+        listing(&self.machine, |index| {
+            let count = self.heat.get(index).copied().unwrap_or(0);
+            let bar = "#".repeat((count * width).div_ceil(max));
+            format!("{count:>10} {bar:<width$} ")
+        })
+    }
+
+    fn report(&mut self, stop: Stop) -> Result<()> {
+        let registers = self.registers();
+        let at = self
+            .machine
+            .pc()
+            .map_or(String::new(), |pc| format!(" at {pc}"));
+        writeln!(
+            self.out,
+            "Stopped ({stop}){at} after {} steps: {registers}",
+            self.steps
+        )?;
+        Ok(())
+    }
+
+    // Commands: step [n], continue, break <index>, delete <index>, watch <register>, regs,
+    // set <register> <value>, trace on|off, list, disasm, heat, quit. Returns false once the session should end
+    pub fn execute(&mut self, command: &str) -> Result<bool> {
+        let mut words = command.split_whitespace();
+        let Some(name) = words.next() else {
+            return Ok(true);
+        };
+        let argument = words.next();
+        match (name, argument) {
+            ("s" | "step", count) => {
+                let count: usize = count.map_or(Ok(1), |c| c.parse())?;
+                let stop = self.resume(Some(count))?;
+                self.report(stop)?;
+            }
+            ("c" | "continue", None) => {
+                let stop = self.resume(None)?;
+                self.report(stop)?;
+            }
+            ("b" | "break", Some(index)) => self.set_breakpoint(index.parse()?)?,
+            ("d" | "delete", Some(index)) => {
+                self.breakpoints.remove(&index.parse()?);
+            }
+            ("w" | "watch", Some(register)) => {
+                let register = register
+                    .chars()
+                    .next()
+                    .with_context(|| "Expected a register name")?;
+                self.watch(register)?;
+            }
+            ("set", Some(register)) => {
+                let register = register
+                    .chars()
+                    .next()
+                    .with_context(|| "Expected a register name")?;
+                let value = words.next().with_context(|| "Expected a value")?;
+                self.machine.set_register(register, value.parse()?)?;
+            }
+            ("r" | "regs", None) => {
+                let registers = self.registers();
+                writeln!(self.out, "{registers}")?;
+            }
+            ("trace", Some("on")) => self.trace = true,
+            ("trace", Some("off")) => self.trace = false,
+            ("l" | "list", None) => {
+                let pc = self.machine.pc();
+                let text = listing(&self.machine, |index| {
+                    if Some(index) == pc { "> " } else { "  " }.to_string()
+                });
+                write!(self.out, "{text}")?;
+            }
+            ("disasm", None) => {
+                let text = disassemble(&self.machine);
+                write!(self.out, "{text}")?;
+            }
+            ("heat", None) => {
+                let text = self.heat_listing();
+                write!(self.out, "{text}")?;
+            }
+            ("q" | "quit", None) => return Ok(false),
+            _ => bail!("Unrecognized debugger command '{command}'"),
+        }
+        Ok(true)
+    }
+
+    pub fn run_script(&mut self, script: &str) -> Result<()> {
+        for command in script.split([';', '\n']) {
+            if !self.execute(command.trim())? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // Bad commands are reported rather than ending the session
+    pub fn run_interactive<R: BufRead>(&mut self, input: R) -> Result<()> {
+        write!(self.out, "(dbg) ")?;
+        self.out.flush()?;
+        for line in input.lines() {
+            match self.execute(line?.trim()) {
+                Ok(false) => break,
+                Ok(true) => {}
+                Err(e) => writeln!(self.out, "{e}")?,
+            }
+            write!(self.out, "(dbg) ")?;
+            self.out.flush()?;
+        }
+        Ok(())
+    }
+
+    // Runs the value of DEBUG_VAR
+    pub fn run(&mut self, script: &str) -> Result<()> {
+        match script {
+            "-" => self.run_interactive(io::stdin().lock()),
+            _ => self.run_script(script),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 0: dec x, 1: jnz x -1, 2: inc y
+    struct Countdown {
+        x: i64,
+        y: i64,
+        pc: usize,
+        // An instruction decode doesn't know about
+        unknown: Option<usize>,
+    }
+
+    impl Debuggable for Countdown {
+        fn pc(&self) -> Option<usize> {
+            (self.pc < 3).then_some(self.pc)
+        }
+
+        fn step(&mut self) -> Result<bool> {
+            match self.pc {
+                0 => self.x -= 1,
+                1 if self.x != 0 => {
+                    self.pc = 0;
+                    return Ok(true);
+                }
+                2 => self.y += 1,
+                _ if self.pc > 2 => return Ok(false),
+                _ => {}
+            }
+            self.pc += 1;
+            Ok(true)
+        }
+
+        fn program_len(&self) -> usize {
+            3
+        }
+
+        fn register_names(&self) -> Vec<char> {
+            vec!['x', 'y']
+        }
+
+        fn register(&self, name: char) -> Option<i64> {
+            match name {
+                'x' => Some(self.x),
+                'y' => Some(self.y),
+                _ => None,
+            }
+        }
+
+        fn set_register(&mut self, name: char, value: i64) -> Result<()> {
+            match name {
+                'x' => self.x = value,
+                'y' => self.y = value,
+                _ => bail!("No register '{name}'"),
+            }
+            Ok(())
+        }
+
+        fn decode(&self, index: usize) -> Option<Decoded> {
+            if self.unknown == Some(index) {
+                return None;
+            }
+            let (text, jump) = match index {
+                0 => ("dec x", None),
+                1 => ("jnz x", Some(0)),
+                2 => ("inc y", None),
+                _ => return None,
+            };
+            Some(Decoded {
+                text: text.to_string(),
+                jump,
+            })
+        }
+    }
+
+    fn debugger(x: i64) -> Debugger<Countdown, Vec<u8>> {
+        Debugger::new(
+            Countdown {
+                x,
+                y: 0,
+                pc: 0,
+                unknown: None,
+            },
+            Vec::new(),
+        )
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let mut machine = Countdown {
+            x: 3,
+            y: 0,
+            pc: 0,
+            unknown: None,
+        };
+        assert_eq!(
+            "L1:     0  dec x\n        1  jnz x L1\n        2  inc y\n",
+            disassemble(&machine)
+        );
+        // Later rows keep their own index and label
+        machine.unknown = Some(0);
+        assert_eq!(
+            "        1  jnz x L1\n        2  inc y\n",
+            disassemble(&machine)
+        );
+    }
+
+    #[test]
+    fn test_breakpoints_and_heatmap() {
+        let mut debugger = debugger(3);
+        debugger.set_breakpoint(1).unwrap();
+        assert!(debugger.set_breakpoint(3).is_err());
+        assert_eq!(Stop::Breakpoint(1), debugger.resume(None).unwrap());
+        assert_eq!(2, debugger.machine.x);
+        // Resuming from a breakpoint runs the instruction under it
+        assert_eq!(Stop::Breakpoint(1), debugger.resume(None).unwrap());
+        assert_eq!(1, debugger.machine.x);
+        assert_eq!(Stop::StepLimit, debugger.resume(Some(1)).unwrap());
+        debugger.breakpoints.clear();
+        assert_eq!(Stop::Halted, debugger.resume(None).unwrap());
+        assert_eq!(vec![3, 3, 1], debugger.heat);
+        assert_eq!(Stop::Halted, debugger.resume(None).unwrap());
+    }
+
+    #[test]
+    fn test_watch() {
+        let mut debugger = debugger(2);
+        assert!(debugger.watch('z').is_err());
+        debugger.watch('y').unwrap();
+        assert_eq!(
+            Stop::Watch {
+                register: 'y',
+                old: 0,
+                new: 1
+            },
+            debugger.resume(None).unwrap()
+        );
+        assert_eq!(5, debugger.steps);
+    }
+
+    #[test]
+    fn test_script() {
+        let mut debugger = debugger(2);
+        assert!(debugger.execute("jump 3").is_err());
+        debugger
+            .run_script("break 2; trace on; continue; trace off; set y 7; regs; heat; quit; step")
+            .unwrap();
+        let out = String::from_utf8(debugger.out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!("       0    0  dec x           x=2 y=0", lines[0]);
+        assert_eq!(
+            "Stopped (breakpoint at 2) at 2 after 4 steps: x=0 y=0",
+            lines[4]
+        );
+        assert_eq!("x=0 y=7", lines[5]);
+        assert_eq!("         2 #################### L1:     0  dec x", lines[6]);
+        assert_eq!(9, lines.len());
+    }
+
+    #[test]
+    fn test_interactive() {
+        let mut debugger = debugger(1);
+        let input = "bogus\nstep 2\nlist\ndisasm\nquit\n".as_bytes();
+        debugger.run_interactive(input).unwrap();
+        let out = String::from_utf8(debugger.out).unwrap();
+        assert!(out.starts_with("(dbg) Unrecognized debugger command 'bogus'\n"));
+        assert!(out.contains("Stopped (step limit) at 2 after 2 steps: x=0 y=0"));
+        assert!(out.contains(">         2  inc y"));
+        assert!(out.contains("(dbg) L1:     0  dec x\n        1  jnz x L1\n"));
+    }
+}
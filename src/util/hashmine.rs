@@ -0,0 +1,170 @@
+use std::collections::VecDeque;
+use std::thread;
+
+// Indices hashed by each thread per batch
+const BATCH_SIZE: usize = 1024;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+// MD5 of a salt followed by a decimal nonce, the salt is only hashed once
+#[derive(Clone)]
+pub struct Salted {
+    context: md5::Context,
+}
+
+impl Salted {
+    pub fn new(salt: &str) -> Self {
+        let mut context = md5::Context::new();
+        context.consume(salt.trim());
+        Salted { context }
+    }
+
+    pub fn digest(&self, nonce: usize) -> [u8; 16] {
+        let mut digits = [0u8; 20];
+        let mut start = digits.len();
+        let mut rest = nonce;
+        loop {
+            start -= 1;
+            digits[start] = b'0' + (rest % 10) as u8;
+            rest /= 10;
+            if rest == 0 {
+                break;
+            }
+        }
+        let mut context = self.context.clone();
+        context.consume(&digits[start..]);
+        context.compute().into()
+    }
+}
+
+pub fn nibble(digest: &[u8; 16], index: usize) -> u8 {
+    let byte = digest[index / 2];
+    if index.is_multiple_of(2) {
+        byte >> 4
+    } else {
+        byte & 0xf
+    }
+}
+
+// Number of leading zeroes in the hex form of the digest
+pub fn leading_zeros(digest: &[u8; 16]) -> usize {
+    (0..32).take_while(|i| nibble(digest, *i) == 0).count()
+}
+
+pub fn to_hex(digest: &[u8; 16]) -> [u8; 32] {
+    let mut hex = [0u8; 32];
+    for (i, digit) in hex.iter_mut().enumerate() {
+        *digit = HEX_DIGITS[nibble(digest, i) as usize];
+    }
+    hex
+}
+
+// Streams the indices (from start onwards) that check accepts, along with what it returned.
+// Batches are checked across all cores, but matches always come out in index order.
+pub struct Mine<T, F> {
+    check: F,
+    next_index: usize,
+    found: VecDeque<(usize, T)>,
+    threads: usize,
+}
+
+pub fn mine<T, F>(start: usize, check: F) -> Mine<T, F>
+where
+    T: Send,
+    F: Fn(usize) -> Option<T> + Sync,
+{
+    Mine {
+        check,
+        next_index: start,
+        found: VecDeque::new(),
+        threads: thread::available_parallelism().map_or(1, |n| n.get()),
+    }
+}
+
+impl<T, F> Mine<T, F>
+where
+    T: Send,
+    F: Fn(usize) -> Option<T> + Sync,
+{
+    fn search_batch(&mut self) {
+        let check = &self.check;
+        let start = self.next_index;
+        let batches: Vec<Vec<(usize, T)>> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..self.threads)
+                .map(|t| {
+                    let from = start + t * BATCH_SIZE;
+                    scope.spawn(move || {
+                        (from..from + BATCH_SIZE)
+                            .filter_map(|i| check(i).map(|found| (i, found)))
+                            .collect()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|h| h.join().expect("Mining thread panicked"))
+                .collect()
+        });
+        self.found.extend(batches.into_iter().flatten());
+        self.next_index += self.threads * BATCH_SIZE;
+    }
+}
+
+impl<T, F> Iterator for Mine<T, F>
+where
+    T: Send,
+    F: Fn(usize) -> Option<T> + Sync,
+{
+    type Item = (usize, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.found.is_empty() {
+            self.search_batch();
+        }
+        self.found.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_digests() {
+        let salted = Salted::new("abc");
+        for nonce in [0, 7, 3231929, usize::MAX] {
+            let expected = md5::compute(format!("abc{nonce}"));
+            assert_eq!(expected.0, salted.digest(nonce));
+            assert_eq!(
+                format!("{expected:x}").as_bytes(),
+                to_hex(&salted.digest(nonce))
+            );
+        }
+        // The first interesting hash from the 2016-05 example
+        let digest = salted.digest(3231929);
+        assert_eq!(5, leading_zeros(&digest));
+        assert_eq!(1, nibble(&digest, 5));
+        assert_eq!(0, leading_zeros(&[0xf0; 16]));
+        assert_eq!(1, leading_zeros(&[0x0f; 16]));
+        assert_eq!(32, leading_zeros(&[0; 16]));
+    }
+
+    #[test]
+    fn test_mine_in_order() {
+        let salted = Salted::new("xyz");
+        let sequential: Vec<(usize, u8)> = (10..20_000)
+            .filter_map(|i| {
+                let digest = salted.digest(i);
+                (digest[0] == 0).then_some((i, digest[1]))
+            })
+            .collect();
+        let mined: Vec<(usize, u8)> = mine(10, |i| {
+            let digest = salted.digest(i);
+            (digest[0] == 0).then_some(digest[1])
+        })
+        .take(sequential.len())
+        .collect();
+        assert!(sequential.len() > 20);
+        assert_eq!(sequential, mined);
+    }
+}
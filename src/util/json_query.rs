@@ -0,0 +1,280 @@
+use std::fmt;
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+
+// Decides whether to skip a value, along with everything inside it
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Filter {
+    // Objects with a property whose value is this string
+    ObjectValue(String),
+    // Objects with a property of this name
+    ObjectKey(String),
+    // Arrays with an element that's this string
+    ArrayValue(String),
+    // Values nested deeper than this, the whole document is at depth 0
+    MaxDepth(usize),
+    Any(Vec<Filter>),
+    All(Vec<Filter>),
+    Not(Box<Filter>),
+}
+
+impl Filter {
+    // Filters separated by commas, any of which skips a value. Each is 'object:X', 'key:X',
+    // 'array:X' or 'depth:N', or combines others as 'any(..)', 'all(..)' or 'not(..)'.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut parser = Parser { text, position: 0 };
+        let filters = parser.list()?;
+        if parser.position < text.len() {
+            bail!("Unexpected '{}' in filter", &text[parser.position..]);
+        }
+        Ok(Filter::Any(filters))
+    }
+
+    pub fn skips(&self, value: &Value, depth: usize) -> bool {
+        match self {
+            Filter::ObjectValue(text) => value
+                .as_object()
+                .is_some_and(|map| map.values().any(|v| v.as_str() == Some(text))),
+            Filter::ObjectKey(key) => value.as_object().is_some_and(|map| map.contains_key(key)),
+            Filter::ArrayValue(text) => value
+                .as_array()
+                .is_some_and(|values| values.iter().any(|v| v.as_str() == Some(text))),
+            Filter::MaxDepth(max) => depth > *max,
+            Filter::Any(filters) => filters.iter().any(|f| f.skips(value, depth)),
+            Filter::All(filters) => filters.iter().all(|f| f.skips(value, depth)),
+            Filter::Not(filter) => !filter.skips(value, depth),
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn rest(&self) -> &str {
+        &self.text[self.position..]
+    }
+
+    fn list(&mut self) -> Result<Vec<Filter>> {
+        let mut filters = vec![self.filter()?];
+        while self.rest().starts_with(',') {
+            self.position += 1;
+            filters.push(self.filter()?);
+        }
+        Ok(filters)
+    }
+
+    fn filter(&mut self) -> Result<Filter> {
+        let start = self.position;
+        let name_len = self
+            .rest()
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(self.rest().len());
+        let name = &self.text[start..start + name_len];
+        self.position += name_len;
+        if self.rest().starts_with('(') {
+            self.position += 1;
+            let filters = self.list()?;
+            if !self.rest().starts_with(')') {
+                bail!("Expected ')' at {} in filter", self.position);
+            }
+            self.position += 1;
+            return match name {
+                "any" => Ok(Filter::Any(filters)),
+                "all" => Ok(Filter::All(filters)),
+                "not" => Ok(Filter::Not(Box::new(Filter::Any(filters)))),
+                _ => bail!("Unknown filter combinator '{name}' at {start}"),
+            };
+        }
+        if !self.rest().starts_with(':') {
+            bail!("Expected ':' or '(' at {} in filter", self.position);
+        }
+        self.position += 1;
+        let argument_len = self
+            .rest()
+            .find([',', '(', ')'])
+            .unwrap_or(self.rest().len());
+        let argument = &self.text[self.position..self.position + argument_len];
+        self.position += argument_len;
+        match name {
+            "object" => Ok(Filter::ObjectValue(argument.to_string())),
+            "key" => Ok(Filter::ObjectKey(argument.to_string())),
+            "array" => Ok(Filter::ArrayValue(argument.to_string())),
+            "depth" => Ok(Filter::MaxDepth(argument.parse().with_context(|| {
+                format!("Depth '{argument}' at {start} isn't a number")
+            })?)),
+            _ => bail!("Unknown filter '{name}' at {start}"),
+        }
+    }
+}
+
+// Where a value is in the document, like $.a[0]["b c"]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Path(Vec<PathPart>);
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum PathPart {
+    Key(String),
+    Index(usize),
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "$")?;
+        for part in &self.0 {
+            match part {
+                PathPart::Index(index) => write!(f, "[{index}]")?,
+                PathPart::Key(key)
+                    if !key.is_empty()
+                        && !key.starts_with(|c: char| c.is_ascii_digit())
+                        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') =>
+                {
+                    write!(f, ".{key}")?
+                }
+                PathPart::Key(key) => write!(f, "[{}]", Value::String(key.clone()))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Summary {
+    pub sum: i64,
+    pub count: usize,
+    // Every number included, in document order (object keys are sorted)
+    pub numbers: Vec<(Path, i64)>,
+}
+
+// Adds up every number not inside a value the filter skips
+pub fn query(document: &Value, filter: &Filter) -> Result<Summary> {
+    let mut summary = Summary::default();
+    visit(document, 0, &mut Path::default(), filter, &mut summary)?;
+    Ok(summary)
+}
+
+fn visit(
+    value: &Value,
+    depth: usize,
+    path: &mut Path,
+    filter: &Filter,
+    summary: &mut Summary,
+) -> Result<()> {
+    if filter.skips(value, depth) {
+        return Ok(());
+    }
+    match value {
+        Value::Number(number) => {
+            let number = number
+                .as_i64()
+                .with_context(|| format!("{number} at {path} isn't a whole number"))?;
+            summary.sum = summary
+                .sum
+                .checked_add(number)
+                .with_context(|| format!("Sum overflows at {path}"))?;
+            summary.count += 1;
+            summary.numbers.push((path.clone(), number));
+        }
+        Value::Array(values) => {
+            for (index, value) in values.iter().enumerate() {
+                path.0.push(PathPart::Index(index));
+                visit(value, depth + 1, path, filter, summary)?;
+                path.0.pop();
+            }
+        }
+        Value::Object(map) => {
+            for (key, value) in map {
+                path.0.push(PathPart::Key(key.clone()));
+                visit(value, depth + 1, path, filter, summary)?;
+                path.0.pop();
+            }
+        }
+        _ => (),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sum(json: &str, filter: &str) -> i64 {
+        query(
+            &serde_json::from_str(json).unwrap(),
+            &Filter::parse(filter).unwrap(),
+        )
+        .unwrap()
+        .sum
+    }
+
+    #[test]
+    fn test_red_examples() {
+        assert_eq!(6, sum("[1,2,3]", "object:red"));
+        assert_eq!(4, sum(r#"[1,{"c":"red","b":2},3]"#, "object:red"));
+        assert_eq!(0, sum(r#"{"d":"red","e":[1,2,3,4],"f":5}"#, "object:red"));
+        assert_eq!(6, sum(r#"[1,"red",5]"#, "object:red"));
+        assert_eq!(0, sum(r#"[1,"red",5]"#, "array:red"));
+    }
+
+    #[test]
+    fn test_composed_filters() {
+        let json = r#"{"a":[1,{"b":2,"c":[3]}],"d":{"e":"red","f":4},"g":10}"#;
+        assert_eq!(16, sum(json, "object:red"));
+        assert_eq!(10, sum(json, "depth:1"));
+        assert_eq!(15, sum(json, "depth:2"));
+        assert_eq!(11, sum(json, "key:b,object:red"));
+        // Only skips objects with both
+        assert_eq!(20, sum(json, "all(key:b,object:red)"));
+        assert_eq!(20, sum(json, "all(key:f,not(object:red))"));
+        assert_eq!(16, sum(json, "all(key:f,not(object:blue))"));
+        // The whole document has a g
+        assert_eq!(0, sum(json, "object:red,key:g"));
+
+        let summary = query(
+            &serde_json::from_str(json).unwrap(),
+            &Filter::parse("object:red").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(4, summary.count);
+        assert_eq!(
+            vec!["$.a[0]", "$.a[1].b", "$.a[1].c[0]", "$.g"],
+            summary
+                .numbers
+                .iter()
+                .map(|(path, _)| path.to_string())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_errors_and_paths() {
+        for bad in [
+            "",
+            "red",
+            "colour:red",
+            "depth:x",
+            "any(object:red",
+            "not(x:1)",
+        ] {
+            assert!(Filter::parse(bad).is_err(), "{bad}");
+        }
+        assert!(Filter::parse("object:red)").is_err());
+        assert!(query(
+            &serde_json::from_str("[1.5]").unwrap(),
+            &Filter::Any(vec![])
+        )
+        .is_err());
+        let summary = query(
+            &serde_json::from_str(r#"{"two words":[{"0":7}]}"#).unwrap(),
+            &Filter::Any(vec![]),
+        )
+        .unwrap();
+        assert_eq!(
+            "$[\"two words\"][0][\"0\"]",
+            summary.numbers[0].0.to_string()
+        );
+    }
+}
@@ -0,0 +1,140 @@
+pub fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+pub fn lcm(a: u64, b: u64) -> u64 {
+    if a == 0 || b == 0 {
+        0
+    } else {
+        a / gcd(a, b) * b
+    }
+}
+
+// Returns (g, x, y) such that a * x + b * y = g = gcd(a, b)
+pub fn extended_gcd(a: i64, b: i64) -> (i64, i64, i64) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x, y) = extended_gcd(b, a % b);
+        (g, y, x - (a / b) * y)
+    }
+}
+
+pub fn mod_pow(base: u64, exp: u64, modulus: u64) -> u64 {
+    if modulus == 1 {
+        return 0;
+    }
+    let modulus = modulus as u128;
+    let mut base = base as u128 % modulus;
+    let mut exp = exp;
+    let mut res: u128 = 1;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            res = res * base % modulus;
+        }
+        base = base * base % modulus;
+        exp >>= 1;
+    }
+    res as u64
+}
+
+// Solves x = residue (mod modulus) for every pair, moduli need not be coprime.
+// Returns (x, lcm of moduli) with 0 <= x < lcm, or None if there is no solution or a
+// modulus is 0.
+pub fn crt(congruences: &[(i64, u64)]) -> Option<(u64, u64)> {
+    let mut res: (u64, u64) = (0, 1);
+    for &(residue, modulus) in congruences {
+        if modulus == 0 {
+            return None;
+        }
+        let (r1, m1) = res;
+        let g = gcd(m1, modulus);
+        let diff = residue as i128 - r1 as i128;
+        if diff % g as i128 != 0 {
+            return None;
+        }
+        let step = (modulus / g) as i128;
+        let (_, inverse, _) =
+            extended_gcd((m1 / g).try_into().ok()?, (modulus / g).try_into().ok()?);
+        let k = (diff / g as i128 % step * inverse as i128).rem_euclid(step);
+        let m = lcm(m1, modulus);
+        res = (
+            ((r1 as i128 + m1 as i128 * k) % m as i128)
+                .try_into()
+                .ok()?,
+            m,
+        );
+    }
+    Some(res)
+}
+
+// Sum of the divisors of every number up to limit, the filter receives (num, divisor)
+pub fn divisor_sums<F>(limit: usize, filter: F) -> Vec<usize>
+where
+    F: Fn(usize, usize) -> bool,
+{
+    let mut sums = vec![0; limit + 1];
+    for d in 1..=limit {
+        for num in (d..=limit).step_by(d) {
+            if filter(num, d) {
+                sums[num] += d;
+            }
+        }
+    }
+    sums
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gcd_lcm() {
+        assert_eq!(6, gcd(54, 24));
+        assert_eq!(7, gcd(7, 0));
+        assert_eq!(216, lcm(54, 24));
+        assert_eq!(0, lcm(0, 5));
+        let (g, x, y) = extended_gcd(240, 46);
+        assert_eq!(2, g);
+        assert_eq!(2, 240 * x + 46 * y);
+    }
+
+    #[test]
+    fn test_mod_pow() {
+        assert_eq!(445, mod_pow(4, 13, 497));
+        assert_eq!(1, mod_pow(3, 0, 7));
+        assert_eq!(0, mod_pow(3, 5, 1));
+        assert_eq!(
+            (u64::MAX as u128 * u64::MAX as u128 % 1_000_000_007) as u64,
+            mod_pow(u64::MAX, 2, 1_000_000_007)
+        );
+    }
+
+    #[test]
+    fn test_crt() {
+        assert_eq!(Some((23, 105)), crt(&[(2, 3), (3, 5), (2, 7)]));
+        // Non-coprime moduli
+        assert_eq!(Some((10, 12)), crt(&[(4, 6), (2, 4)]));
+        assert_eq!(None, crt(&[(1, 6), (2, 4)]));
+        assert_eq!(Some((0, 1)), crt(&[]));
+        // Residues outside of [0, modulus)
+        assert_eq!(Some((4, 5)), crt(&[(-1, 5), (14, 5)]));
+        assert_eq!(None, crt(&[(0, 0)]));
+        assert_eq!(None, crt(&[(2, 3), (1, 0)]));
+    }
+
+    #[test]
+    fn test_divisor_sums() {
+        let sums = divisor_sums(12, |_, _| true);
+        assert_eq!(
+            &[0, 1, 3, 4, 7, 6, 12, 8, 15, 13, 18, 12, 28],
+            sums.as_slice()
+        );
+        let limited = divisor_sums(12, |num, d| d * 2 >= num);
+        assert_eq!(6 + 12, limited[12]);
+    }
+}
@@ -1,8 +1,18 @@
 pub mod a_star;
 pub mod cardinal;
+pub mod composition;
+pub mod debugger;
 pub mod expand;
+pub mod hashmine;
 pub mod input;
 pub mod is_hex;
+pub mod json_query;
+pub mod math;
 pub mod ordered_vec;
-pub mod peek;
+pub mod polyline;
 pub mod priority_queue;
+#[cfg(test)]
+pub mod random;
+pub mod register_machine;
+pub mod search;
+pub mod tsp;
@@ -0,0 +1,260 @@
+use crate::data::Point2D;
+use crate::util::cardinal::Cardinal;
+
+// An axis aligned straight line, which visits every point after start up to its length
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Segment {
+    pub start: Point2D<i64>,
+    pub direction: Cardinal,
+    pub length: i64,
+}
+
+impl Segment {
+    pub fn at(&self, distance: i64) -> Point2D<i64> {
+        self.start + self.direction.offset(distance)
+    }
+
+    pub fn end(&self) -> Point2D<i64> {
+        self.at(self.length)
+    }
+
+    // The corners of the box around the points visited, None if there aren't any
+    fn visited(&self) -> Option<(Point2D<i64>, Point2D<i64>)> {
+        if self.length == 0 {
+            return None;
+        }
+        let (first, end) = (self.at(1), self.end());
+        Some((
+            Point2D::new(first.x.min(end.x), first.y.min(end.y)),
+            Point2D::new(first.x.max(end.x), first.y.max(end.y)),
+        ))
+    }
+
+    // The distances along this segment (from 1 up to its length) of the points inside the
+    // box, as an inclusive range
+    fn distances_within(&self, low: Point2D<i64>, high: Point2D<i64>) -> Option<(i64, i64)> {
+        let (dx, dy): (i64, i64) = self.direction.unit();
+        let (mut from, mut to) = (1, self.length);
+        for (start, step, low, high) in [
+            (self.start.x, dx, low.x, high.x),
+            (self.start.y, dy, low.y, high.y),
+        ] {
+            if step == 0 {
+                if start < low || start > high {
+                    return None;
+                }
+            } else {
+                let (a, b) = ((low - start) * step, (high - start) * step);
+                from = from.max(a.min(b));
+                to = to.min(a.max(b));
+            }
+        }
+        (from <= to).then_some((from, to))
+    }
+}
+
+// A point the route comes back to, and how far along the route it does
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Revisit {
+    pub point: Point2D<i64>,
+    pub distance: i64,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Polyline {
+    pub origin: Point2D<i64>,
+    pub segments: Vec<Segment>,
+}
+
+impl Polyline {
+    pub fn new(origin: Point2D<i64>) -> Self {
+        Polyline {
+            origin,
+            segments: Vec::new(),
+        }
+    }
+
+    pub fn end(&self) -> Point2D<i64> {
+        self.segments.last().map_or(self.origin, |s| s.end())
+    }
+
+    pub fn push(&mut self, direction: Cardinal, length: i64) {
+        let start = self.end();
+        self.segments.push(Segment {
+            start,
+            direction,
+            length,
+        });
+    }
+
+    // Boxes around the points visited before each segment, starting with the origin
+    fn visited_before(
+        &self,
+        index: usize,
+    ) -> impl Iterator<Item = (Point2D<i64>, Point2D<i64>)> + '_ {
+        std::iter::once((self.origin, self.origin))
+            .chain(self.segments[..index].iter().filter_map(|s| s.visited()))
+    }
+
+    // Where the route first comes back to somewhere it's already been, including crossing
+    // part way along a segment, without visiting every point
+    pub fn first_revisit(&self) -> Option<Revisit> {
+        let mut travelled = 0;
+        for (index, segment) in self.segments.iter().enumerate() {
+            let first = self
+                .visited_before(index)
+                .filter_map(|(low, high)| segment.distances_within(low, high))
+                .map(|(from, _)| from)
+                .min();
+            if let Some(distance) = first {
+                return Some(Revisit {
+                    point: segment.at(distance),
+                    distance: travelled + distance,
+                });
+            }
+            travelled += segment.length;
+        }
+        None
+    }
+
+    // Every time the route comes back to somewhere it's already been, in route order
+    pub fn revisits(&self) -> Vec<Revisit> {
+        let mut revisits = Vec::new();
+        let mut travelled = 0;
+        for (index, segment) in self.segments.iter().enumerate() {
+            let mut ranges: Vec<(i64, i64)> = self
+                .visited_before(index)
+                .filter_map(|(low, high)| segment.distances_within(low, high))
+                .collect();
+            ranges.sort_unstable();
+            // Earlier segments can overlap each other, so only count each point once
+            let mut next = 1;
+            for (from, to) in ranges {
+                for distance in from.max(next)..=to {
+                    revisits.push(Revisit {
+                        point: segment.at(distance),
+                        distance: travelled + distance,
+                    });
+                }
+                next = next.max(to + 1);
+            }
+            travelled += segment.length;
+        }
+        revisits
+    }
+
+    // The route drawn with - and | where it runs and + where it turns or crosses, S at
+    // the origin, E at the end and X where it first comes back to somewhere. North is up.
+    pub fn render(&self) -> String {
+        let points = self.segments.iter().map(|s| s.end()).chain([self.origin]);
+        let (mut low, mut high) = (self.origin, self.origin);
+        for point in points {
+            low = Point2D::new(low.x.min(point.x), low.y.min(point.y));
+            high = Point2D::new(high.x.max(point.x), high.y.max(point.y));
+        }
+        let width = (high.x - low.x + 1) as usize;
+        let height = (high.y - low.y + 1) as usize;
+        // Whether anything runs across and up and down through each cell
+        let mut cells = vec![(false, false); width * height];
+        let index = |p: Point2D<i64>| (p.y - low.y) as usize * width + (p.x - low.x) as usize;
+        for segment in self.segments.iter().filter(|s| s.length > 0) {
+            let across = matches!(segment.direction, Cardinal::East | Cardinal::West);
+            for distance in 0..=segment.length {
+                let cell = &mut cells[index(segment.at(distance))];
+                if across {
+                    cell.0 = true;
+                } else {
+                    cell.1 = true;
+                }
+            }
+        }
+        let mut grid: Vec<char> = cells
+            .iter()
+            .map(|cell| match cell {
+                (true, true) => '+',
+                (true, false) => '-',
+                (false, true) => '|',
+                (false, false) => ' ',
+            })
+            .collect();
+        if let Some(revisit) = self.first_revisit() {
+            grid[index(revisit.point)] = 'X';
+        }
+        grid[index(self.end())] = 'E';
+        grid[index(self.origin)] = 'S';
+        grid.chunks(width)
+            .map(|row| row.iter().collect::<String>().trim_end().to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::random::generator;
+    use std::collections::HashSet;
+
+    // Steps along the route a point at a time, for checking against
+    fn stepped_revisits(route: &[(Cardinal, i64)]) -> Vec<Revisit> {
+        let mut position: Point2D<i64> = Point2D::new(0, 0);
+        let mut seen = HashSet::from([position]);
+        let mut revisits = Vec::new();
+        let mut distance = 0;
+        for (direction, length) in route {
+            for _ in 0..*length {
+                position += direction.unit();
+                distance += 1;
+                if !seen.insert(position) {
+                    revisits.push(Revisit {
+                        point: position,
+                        distance,
+                    });
+                }
+            }
+        }
+        revisits
+    }
+
+    fn polyline(route: &[(Cardinal, i64)]) -> Polyline {
+        let mut polyline = Polyline::new(Point2D::new(0, 0));
+        for (direction, length) in route {
+            polyline.push(*direction, *length);
+        }
+        polyline
+    }
+
+    #[test]
+    fn test_matches_stepping() {
+        let mut next = generator(1);
+        for _ in 0..200 {
+            // Any direction, so the route can double back over itself too
+            let route: Vec<(Cardinal, i64)> = (0..(next() % 12))
+                .map(|_| (Cardinal::ALL[(next() % 4) as usize], (next() % 6) as i64))
+                .collect();
+            let expected = stepped_revisits(&route);
+            let polyline = polyline(&route);
+            assert_eq!(expected, polyline.revisits(), "{route:?}");
+            assert_eq!(expected.first().copied(), polyline.first_revisit());
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let route = polyline(&[
+            (Cardinal::East, 4),
+            (Cardinal::South, 2),
+            (Cardinal::West, 2),
+            (Cardinal::North, 3),
+        ]);
+        assert_eq!(
+            Some(Revisit {
+                point: Point2D::new(2, 0),
+                distance: 10
+            }),
+            route.first_revisit()
+        );
+        assert_eq!("  E\nS-X-+\n  | |\n  +-+", route.render());
+        assert_eq!("S", polyline(&[]).render());
+    }
+}
@@ -67,10 +67,6 @@ where
     pub fn pop(&mut self) -> Option<(T, P)> {
         self.que.pop().map(|item| (item.value, item.priority))
     }
-
-    pub fn len(&self) -> usize {
-        self.que.len()
-    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,9 @@
+// Deterministic pseudo random numbers for tests, from a 64 bit linear congruential generator
+pub fn generator(mut seed: u64) -> impl FnMut() -> u64 {
+    move || {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        seed >> 33
+    }
+}
@@ -0,0 +1,335 @@
+use std::fmt;
+use std::num::Wrapping;
+
+use anyhow::{Context, Result};
+
+use crate::util::debugger::{Debuggable, Decoded};
+
+// Register values, the try_ operations return None on overflow (or division by zero).
+// Primitive integers are checked, std::num::Wrapping integers wrap around instead.
+pub trait Word: Copy + Default + Eq + fmt::Debug + fmt::Display {
+    fn from_i64(value: i64) -> Option<Self>;
+    fn to_i64(self) -> Option<i64>;
+    fn try_add(self, rhs: Self) -> Option<Self>;
+    fn try_sub(self, rhs: Self) -> Option<Self>;
+    fn try_mul(self, rhs: Self) -> Option<Self>;
+    fn try_div(self, rhs: Self) -> Option<Self>;
+}
+
+macro_rules! impl_word {
+    ($($t:ty),*) => {
+        $(
+            impl Word for $t {
+                fn from_i64(value: i64) -> Option<Self> {
+                    value.try_into().ok()
+                }
+
+                fn to_i64(self) -> Option<i64> {
+                    self.try_into().ok()
+                }
+
+                fn try_add(self, rhs: Self) -> Option<Self> {
+                    self.checked_add(rhs)
+                }
+
+                fn try_sub(self, rhs: Self) -> Option<Self> {
+                    self.checked_sub(rhs)
+                }
+
+                fn try_mul(self, rhs: Self) -> Option<Self> {
+                    self.checked_mul(rhs)
+                }
+
+                fn try_div(self, rhs: Self) -> Option<Self> {
+                    self.checked_div(rhs)
+                }
+            }
+
+            impl Word for Wrapping<$t> {
+                fn from_i64(value: i64) -> Option<Self> {
+                    Some(Wrapping(value as $t))
+                }
+
+                fn to_i64(self) -> Option<i64> {
+                    self.0.try_into().ok()
+                }
+
+                fn try_add(self, rhs: Self) -> Option<Self> {
+                    Some(self + rhs)
+                }
+
+                fn try_sub(self, rhs: Self) -> Option<Self> {
+                    Some(self - rhs)
+                }
+
+                fn try_mul(self, rhs: Self) -> Option<Self> {
+                    Some(self * rhs)
+                }
+
+                fn try_div(self, rhs: Self) -> Option<Self> {
+                    (rhs.0 != 0).then(|| Wrapping(self.0.wrapping_div(rhs.0)))
+                }
+            }
+        )*
+    };
+}
+
+impl_word!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+// What the program counter does after an instruction
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Flow {
+    Next,
+    // Relative to the current instruction
+    Jump(i64),
+}
+
+pub trait Instruction<W: Word, const N: usize>: Copy {
+    fn execute(self, vm: &mut Vm<Self, W, N>) -> Result<Flow>;
+    fn decode(&self, index: usize) -> Decoded;
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Halt {
+    // Program counter left the program
+    Finished,
+    // The stop condition passed to run_until was met
+    Stopped,
+    StepLimit,
+}
+
+// Registers are named 'a', 'b', ...
+pub fn register_index(name: char, count: usize) -> Option<usize> {
+    (name as usize)
+        .checked_sub('a' as usize)
+        .filter(|index| *index < count)
+}
+
+#[derive(Clone, Debug)]
+pub struct Vm<I, W, const N: usize> {
+    pub program: Vec<I>,
+    pub registers: [W; N],
+    // Any jump outside of the program halts it, so the counter may go negative
+    pub pc: i64,
+    pub steps: usize,
+    pub output: Vec<W>,
+}
+
+impl<I: Instruction<W, N>, W: Word, const N: usize> Vm<I, W, N> {
+    pub fn new(program: &[I]) -> Self {
+        Vm {
+            program: program.to_vec(),
+            registers: [W::default(); N],
+            pc: 0,
+            steps: 0,
+            output: Vec::new(),
+        }
+    }
+
+    pub fn with_register(mut self, name: char, value: W) -> Result<Self> {
+        self.registers[Self::register_index(name)?] = value;
+        Ok(self)
+    }
+
+    pub fn register(&self, name: char) -> Result<W> {
+        Ok(self.registers[Self::register_index(name)?])
+    }
+
+    fn register_index(name: char) -> Result<usize> {
+        register_index(name, N).with_context(|| format!("No register '{name}'"))
+    }
+
+    pub fn index(&self, pc: i64) -> Option<usize> {
+        usize::try_from(pc)
+            .ok()
+            .filter(|index| *index < self.program.len())
+    }
+
+    pub fn literal(&self, value: i64) -> Result<W> {
+        W::from_i64(value).with_context(|| format!("{value} doesn't fit a register"))
+    }
+
+    pub fn add(&self, a: W, b: W) -> Result<W> {
+        a.try_add(b)
+            .with_context(|| format!("Overflow on {a} + {b} at {}", self.pc))
+    }
+
+    pub fn sub(&self, a: W, b: W) -> Result<W> {
+        a.try_sub(b)
+            .with_context(|| format!("Overflow on {a} - {b} at {}", self.pc))
+    }
+
+    pub fn mul(&self, a: W, b: W) -> Result<W> {
+        a.try_mul(b)
+            .with_context(|| format!("Overflow on {a} * {b} at {}", self.pc))
+    }
+
+    pub fn div(&self, a: W, b: W) -> Result<W> {
+        a.try_div(b)
+            .with_context(|| format!("Can't divide {a} by {b} at {}", self.pc))
+    }
+
+    // Returns false once the program has halted
+    pub fn step(&mut self) -> Result<bool> {
+        let Some(index) = self.index(self.pc) else {
+            return Ok(false);
+        };
+        self.pc = match self.program[index].execute(self)? {
+            Flow::Next => self.pc + 1,
+            Flow::Jump(offset) => self.pc.saturating_add(offset),
+        };
+        self.steps += 1;
+        Ok(true)
+    }
+
+    pub fn run_until<F>(&mut self, stop: F, step_limit: Option<usize>) -> Result<Halt>
+    where
+        F: Fn(&Self) -> bool,
+    {
+        loop {
+            if stop(self) {
+                return Ok(Halt::Stopped);
+            }
+            if step_limit.is_some_and(|limit| self.steps >= limit) {
+                return Ok(Halt::StepLimit);
+            }
+            if !self.step()? {
+                return Ok(Halt::Finished);
+            }
+        }
+    }
+
+    pub fn run(&mut self, step_limit: Option<usize>) -> Result<Halt> {
+        self.run_until(|_| false, step_limit)
+    }
+}
+
+impl<I: Instruction<W, N>, W: Word, const N: usize> Debuggable for Vm<I, W, N> {
+    fn pc(&self) -> Option<usize> {
+        self.index(self.pc)
+    }
+
+    fn step(&mut self) -> Result<bool> {
+        Vm::step(self)
+    }
+
+    fn program_len(&self) -> usize {
+        self.program.len()
+    }
+
+    fn register_names(&self) -> Vec<char> {
+        ('a'..='z').take(N).collect()
+    }
+
+    fn register(&self, name: char) -> Option<i64> {
+        self.registers[register_index(name, N)?].to_i64()
+    }
+
+    fn set_register(&mut self, name: char, value: i64) -> Result<()> {
+        let index = Self::register_index(name)?;
+        self.registers[index] = self.literal(value)?;
+        Ok(())
+    }
+
+    fn decode(&self, index: usize) -> Option<Decoded> {
+        Some(self.program.get(index)?.decode(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A tiny instruction set: add a literal to a, double a, jump back while a is below a limit
+    #[derive(Copy, Clone)]
+    enum Toy {
+        Add(i64),
+        Double,
+        JumpIfBelow(i64, i64),
+    }
+
+    impl<W: Word + Ord> Instruction<W, 1> for Toy {
+        fn execute(self, vm: &mut Vm<Self, W, 1>) -> Result<Flow> {
+            match self {
+                Toy::Add(x) => vm.registers[0] = vm.add(vm.registers[0], vm.literal(x)?)?,
+                Toy::Double => vm.registers[0] = vm.mul(vm.registers[0], vm.literal(2)?)?,
+                Toy::JumpIfBelow(limit, offset) => {
+                    if vm.registers[0] < vm.literal(limit)? {
+                        return Ok(Flow::Jump(offset));
+                    }
+                }
+            }
+            Ok(Flow::Next)
+        }
+
+        fn decode(&self, _: usize) -> Decoded {
+            Decoded {
+                text: String::new(),
+                jump: None,
+            }
+        }
+    }
+
+    #[test]
+    fn test_run() {
+        let program = [Toy::Add(1), Toy::Double, Toy::JumpIfBelow(100, -2)];
+        let mut vm: Vm<Toy, u32, 1> = Vm::new(&program);
+        assert_eq!(Halt::Finished, vm.run(None).unwrap());
+        assert_eq!(126, vm.register('a').unwrap());
+        assert_eq!(18, vm.steps);
+        let mut vm: Vm<Toy, u32, 1> = Vm::new(&program);
+        assert_eq!(Halt::StepLimit, vm.run(Some(4)).unwrap());
+        assert_eq!(Halt::Stopped, vm.run_until(|vm| vm.pc == 2, None).unwrap());
+        assert_eq!(6, vm.register('a').unwrap());
+        assert!(vm.register('b').is_err());
+        assert!(vm.with_register('b', 1).is_err());
+    }
+
+    #[test]
+    fn test_out_of_range_jumps_halt() {
+        for offset in [-3, 3, i64::MIN, i64::MAX] {
+            let mut vm: Vm<Toy, i64, 1> = Vm::new(&[Toy::JumpIfBelow(1, offset), Toy::Add(5)]);
+            assert_eq!(Halt::Finished, vm.run(Some(10)).unwrap());
+            assert_eq!(0, vm.register('a').unwrap());
+            assert_eq!(1, vm.steps);
+        }
+    }
+
+    #[test]
+    fn test_checked_and_wrapping() {
+        let program = [Toy::Add(200), Toy::Double];
+        let mut checked: Vm<Toy, u8, 1> = Vm::new(&program);
+        let error = checked.run(None).unwrap_err();
+        assert_eq!("Overflow on 200 * 2 at 1", error.to_string());
+        let mut wrapping: Vm<Toy, Wrapping<u8>, 1> = Vm::new(&program);
+        assert_eq!(Halt::Finished, wrapping.run(None).unwrap());
+        assert_eq!(Wrapping(144), wrapping.register('a').unwrap());
+        // Literals that don't fit are truncated when wrapping
+        let mut vm: Vm<Toy, Wrapping<u8>, 1> = Vm::new(&[Toy::Add(-1)]);
+        vm.run(None).unwrap();
+        assert_eq!(Wrapping(255), vm.register('a').unwrap());
+        let mut vm: Vm<Toy, u8, 1> = Vm::new(&[Toy::Add(-1)]);
+        assert!(vm.run(None).is_err());
+    }
+
+    #[test]
+    fn test_debuggable() {
+        let mut vm: Vm<Toy, u8, 1> = Vm::new(&[Toy::Double]);
+        assert_eq!(vec!['a'], vm.register_names());
+        vm.set_register('a', 21).unwrap();
+        assert!(vm.set_register('a', 256).is_err());
+        assert!(vm.set_register('b', 1).is_err());
+        assert_eq!(Some(0), Debuggable::pc(&vm));
+        assert!(Debuggable::step(&mut vm).unwrap());
+        assert_eq!(Some(42), Debuggable::register(&vm, 'a'));
+        assert_eq!(None, Debuggable::pc(&vm));
+    }
+
+    #[test]
+    fn test_register_index() {
+        assert_eq!(Some(0), register_index('a', 2));
+        assert_eq!(Some(1), register_index('b', 2));
+        assert_eq!(None, register_index('c', 2));
+        assert_eq!(None, register_index('A', 2));
+    }
+}
@@ -0,0 +1,419 @@
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::str::FromStr;
+
+use anyhow::{bail, Error, Result};
+
+use crate::util::priority_queue::PriorityQueue;
+
+// A puzzle searched one move at a time, where every move costs the same
+pub trait Puzzle {
+    type State: Clone;
+    type Move: Clone;
+    type Key: Clone + Eq + Hash;
+
+    // Each move from state and the state it leads to, valid or not
+    fn moves(&self, state: &Self::State) -> Vec<(Self::Move, Self::State)>;
+    fn valid(&self, state: &Self::State) -> bool;
+    fn is_goal(&self, state: &Self::State) -> bool;
+    // States with the same key are interchangeable, like ones that differ only by a symmetry
+    fn key(&self, state: &Self::State) -> Self::Key;
+    // A lower bound on the moves left, overestimating means A* might miss the shortest
+    fn heuristic(&self, _state: &Self::State) -> usize {
+        0
+    }
+}
+
+// A puzzle where every move can be undone and there's one goal, so it can be searched
+// from both ends
+pub trait Reversible: Puzzle {
+    fn goal(&self, start: &Self::State) -> Self::State;
+}
+
+#[derive(Clone, Debug)]
+pub struct Solution<M, S> {
+    // Each move along with the state it leads to
+    pub steps: Vec<(M, S)>,
+    // How many states the search reached
+    pub explored: usize,
+}
+
+fn successors<P: Puzzle>(puzzle: &P, state: &P::State) -> Vec<(P::Move, P::State)> {
+    let mut moves = puzzle.moves(state);
+    moves.retain(|(_, next)| puzzle.valid(next));
+    moves
+}
+
+struct Node<P: Puzzle> {
+    state: P::State,
+    parent: Option<(usize, P::Move)>,
+    depth: usize,
+}
+
+// Every state reached, and how
+struct Tree<P: Puzzle> {
+    nodes: Vec<Node<P>>,
+}
+
+impl<P: Puzzle> Tree<P> {
+    fn new(root: P::State) -> Self {
+        Tree {
+            nodes: vec![Node {
+                state: root,
+                parent: None,
+                depth: 0,
+            }],
+        }
+    }
+
+    fn add(&mut self, state: P::State, parent: usize, step: P::Move) -> usize {
+        let depth = self.nodes[parent].depth + 1;
+        self.nodes.push(Node {
+            state,
+            parent: Some((parent, step)),
+            depth,
+        });
+        self.nodes.len() - 1
+    }
+
+    fn path(&self, mut index: usize) -> Vec<(P::Move, P::State)> {
+        let mut steps = Vec::new();
+        while let Some((parent, step)) = &self.nodes[index].parent {
+            steps.push((step.clone(), self.nodes[index].state.clone()));
+            index = *parent;
+        }
+        steps.reverse();
+        steps
+    }
+}
+
+pub fn breadth_first<P: Puzzle>(
+    puzzle: &P,
+    start: P::State,
+) -> Option<Solution<P::Move, P::State>> {
+    let mut seen: HashMap<P::Key, usize> = HashMap::new();
+    seen.insert(puzzle.key(&start), 0);
+    let mut tree = Tree::<P>::new(start);
+    let mut next_index = 0;
+    while next_index < tree.nodes.len() {
+        let current = next_index;
+        next_index += 1;
+        if puzzle.is_goal(&tree.nodes[current].state) {
+            return Some(Solution {
+                steps: tree.path(current),
+                explored: tree.nodes.len(),
+            });
+        }
+        for (step, next) in successors(puzzle, &tree.nodes[current].state) {
+            if let Entry::Vacant(entry) = seen.entry(puzzle.key(&next)) {
+                entry.insert(tree.add(next, current, step));
+            }
+        }
+    }
+    None
+}
+
+pub fn a_star<P: Puzzle>(puzzle: &P, start: P::State) -> Option<Solution<P::Move, P::State>> {
+    // Fewest moves found to each key, with the node that got there
+    let mut best: HashMap<P::Key, (usize, usize)> = HashMap::new();
+    best.insert(puzzle.key(&start), (0, 0));
+    let mut frontier = PriorityQueue::new();
+    // Lowest estimate first, the deepest of those to head for the goal
+    frontier.push(0, (Reverse(puzzle.heuristic(&start)), 0));
+    let mut tree = Tree::<P>::new(start);
+    while let Some((current, _)) = frontier.pop() {
+        let node = &tree.nodes[current];
+        // Skip anything since reached in fewer moves
+        if best[&puzzle.key(&node.state)].1 != current {
+            continue;
+        }
+        if puzzle.is_goal(&node.state) {
+            return Some(Solution {
+                steps: tree.path(current),
+                explored: tree.nodes.len(),
+            });
+        }
+        let depth = node.depth + 1;
+        for (step, next) in successors(puzzle, &node.state) {
+            let key = puzzle.key(&next);
+            if best.get(&key).is_some_and(|(moves, _)| *moves <= depth) {
+                continue;
+            }
+            let estimate = depth + puzzle.heuristic(&next);
+            let index = tree.add(next, current, step);
+            best.insert(key, (depth, index));
+            frontier.push(index, (Reverse(estimate), depth));
+        }
+    }
+    None
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum Strategy {
+    BreadthFirst,
+    #[default]
+    AStar,
+    Bidirectional,
+}
+
+impl FromStr for Strategy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "bfs" | "breadth-first" => Ok(Strategy::BreadthFirst),
+            "astar" | "a*" => Ok(Strategy::AStar),
+            "bidirectional" => Ok(Strategy::Bidirectional),
+            _ => bail!("Unknown search strategy '{s}'"),
+        }
+    }
+}
+
+pub fn solve<P: Reversible>(
+    puzzle: &P,
+    start: P::State,
+    strategy: Strategy,
+) -> Option<Solution<P::Move, P::State>> {
+    match strategy {
+        Strategy::BreadthFirst => breadth_first(puzzle, start),
+        Strategy::AStar => a_star(puzzle, start),
+        Strategy::Bidirectional => bidirectional(puzzle, start),
+    }
+}
+
+// Where the two searches met
+struct Meeting<M, S> {
+    length: usize,
+    // The moves from the start up to the meeting
+    forward: Vec<(M, S)>,
+    // The first state on the way back to the goal after the meeting, if any
+    backward: Option<usize>,
+}
+
+// Breadth first from the start and back from the goal a layer at a time, expanding
+// whichever side has fewer states waiting
+pub fn bidirectional<P: Reversible>(
+    puzzle: &P,
+    start: P::State,
+) -> Option<Solution<P::Move, P::State>> {
+    let goal = puzzle.goal(&start);
+    if !puzzle.valid(&goal) || !puzzle.is_goal(&goal) {
+        return None;
+    }
+    if puzzle.key(&start) == puzzle.key(&goal) {
+        return Some(Solution {
+            steps: Vec::new(),
+            explored: 1,
+        });
+    }
+    let mut trees = [Tree::<P>::new(start.clone()), Tree::<P>::new(goal.clone())];
+    let mut seen: [HashMap<P::Key, usize>; 2] = [
+        HashMap::from([(puzzle.key(&start), 0)]),
+        HashMap::from([(puzzle.key(&goal), 0)]),
+    ];
+    let mut layers: [Vec<usize>; 2] = [vec![0], vec![0]];
+    while !layers[0].is_empty() && !layers[1].is_empty() {
+        let side = usize::from(layers[1].len() < layers[0].len());
+        let other = 1 - side;
+        let mut meeting: Option<Meeting<P::Move, P::State>> = None;
+        let mut next_layer = Vec::new();
+        for current in std::mem::take(&mut layers[side]) {
+            for (step, next) in successors(puzzle, &trees[side].nodes[current].state) {
+                let key = puzzle.key(&next);
+                if let Some(&met) = seen[other].get(&key) {
+                    let length =
+                        trees[side].nodes[current].depth + 1 + trees[other].nodes[met].depth;
+                    if meeting.as_ref().is_some_and(|m| m.length <= length) {
+                        continue;
+                    }
+                    meeting = Some(if side == 0 {
+                        let mut forward = trees[0].path(current);
+                        forward.push((step, next));
+                        Meeting {
+                            length,
+                            forward,
+                            backward: trees[1].nodes[met].parent.as_ref().map(|(p, _)| *p),
+                        }
+                    } else {
+                        Meeting {
+                            length,
+                            forward: trees[0].path(met),
+                            backward: Some(current),
+                        }
+                    });
+                } else if !seen[side].contains_key(&key) {
+                    let index = trees[side].add(next, current, step);
+                    seen[side].insert(key, index);
+                    next_layer.push(index);
+                }
+            }
+        }
+        layers[side] = next_layer;
+        if let Some(meeting) = meeting {
+            let explored = trees[0].nodes.len() + trees[1].nodes.len();
+            let steps = follow_back(puzzle, &trees[1], &start, meeting)?;
+            return Some(Solution { steps, explored });
+        }
+    }
+    None
+}
+
+// The backward search's states might only match the forward ones by key, so finds moves
+// from the meeting that reach each of their keys in turn
+fn follow_back<P: Puzzle>(
+    puzzle: &P,
+    backward: &Tree<P>,
+    start: &P::State,
+    meeting: Meeting<P::Move, P::State>,
+) -> Option<Vec<(P::Move, P::State)>> {
+    let mut steps = meeting.forward;
+    let mut current = steps.last().map_or(start.clone(), |(_, s)| s.clone());
+    let mut index = meeting.backward;
+    while let Some(i) = index {
+        let target = puzzle.key(&backward.nodes[i].state);
+        let (step, next) = successors(puzzle, &current)
+            .into_iter()
+            .find(|(_, next)| puzzle.key(next) == target)?;
+        steps.push((step, next.clone()));
+        current = next;
+        index = backward.nodes[i].parent.as_ref().map(|(p, _)| *p);
+    }
+    Some(steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Walk a number line by 1s and 5s without landing on multiples of 7, to the target
+    struct Walk {
+        target: i32,
+    }
+
+    impl Puzzle for Walk {
+        type State = i32;
+        type Move = i32;
+        type Key = i32;
+
+        fn moves(&self, state: &i32) -> Vec<(i32, i32)> {
+            [-5, -1, 1, 5].iter().map(|m| (*m, state + m)).collect()
+        }
+
+        fn valid(&self, state: &i32) -> bool {
+            (0..=40).contains(state) && state % 7 != 0
+        }
+
+        fn is_goal(&self, state: &i32) -> bool {
+            *state == self.target
+        }
+
+        fn key(&self, state: &i32) -> i32 {
+            *state
+        }
+
+        fn heuristic(&self, state: &i32) -> usize {
+            (self.target - state).unsigned_abs().div_ceil(5) as usize
+        }
+    }
+
+    impl Reversible for Walk {
+        fn goal(&self, _start: &i32) -> i32 {
+            self.target
+        }
+    }
+
+    // Two counters that each go up or down, where it doesn't matter which is which
+    struct Counters;
+
+    impl Puzzle for Counters {
+        type State = (u8, u8);
+        type Move = bool;
+        type Key = (u8, u8);
+
+        fn moves(&self, state: &(u8, u8)) -> Vec<(bool, (u8, u8))> {
+            let (a, b) = *state;
+            let mut moves = vec![(true, (a + 1, b)), (false, (a, b + 1))];
+            if a > 0 {
+                moves.push((true, (a - 1, b)));
+            }
+            if b > 0 {
+                moves.push((false, (a, b - 1)));
+            }
+            moves
+        }
+
+        fn valid(&self, state: &(u8, u8)) -> bool {
+            state.0 <= 6 && state.1 <= 6
+        }
+
+        fn is_goal(&self, state: &(u8, u8)) -> bool {
+            self.key(state) == (3, 6)
+        }
+
+        fn key(&self, state: &(u8, u8)) -> (u8, u8) {
+            (state.0.min(state.1), state.0.max(state.1))
+        }
+    }
+
+    impl Reversible for Counters {
+        fn goal(&self, _start: &(u8, u8)) -> (u8, u8) {
+            (6, 3)
+        }
+    }
+
+    fn check_walk(start: i32, steps: &[(i32, i32)], walk: &Walk) {
+        let mut current = start;
+        for (step, next) in steps {
+            current += step;
+            assert_eq!(current, *next);
+            assert!(walk.valid(next));
+        }
+        assert!(walk.is_goal(&current));
+    }
+
+    #[test]
+    fn test_strategies_agree() {
+        for target in [1, 13, 24, 33, 40] {
+            let walk = Walk { target };
+            for start in (1..40).filter(|s| walk.valid(s)) {
+                let bfs = breadth_first(&walk, start).unwrap();
+                check_walk(start, &bfs.steps, &walk);
+                let a_star = a_star(&walk, start).unwrap();
+                check_walk(start, &a_star.steps, &walk);
+                assert_eq!(bfs.steps.len(), a_star.steps.len());
+                let both = bidirectional(&walk, start).unwrap();
+                check_walk(start, &both.steps, &walk);
+                assert_eq!(bfs.steps.len(), both.steps.len(), "{start} to {target}");
+            }
+        }
+        // Multiples of 7 are never valid
+        assert!(breadth_first(&Walk { target: 35 }, 1).is_none());
+        assert!(a_star(&Walk { target: 35 }, 1).is_none());
+        assert!(bidirectional(&Walk { target: 35 }, 1).is_none());
+    }
+
+    #[test]
+    fn test_keys_merge_states() {
+        let solution = breadth_first(&Counters, (0, 0)).unwrap();
+        // Only the 28 keys, not all 49 states
+        assert!(solution.explored <= 28);
+        // The backward search's states get matched up by key
+        let both = bidirectional(&Counters, (0, 0)).unwrap();
+        for solution in [solution, both] {
+            assert_eq!(9, solution.steps.len());
+            let mut current: (u8, u8) = (0, 0);
+            for (first, next) in &solution.steps {
+                let changed = if *first {
+                    current.0.abs_diff(next.0) + u8::from(current.1 != next.1)
+                } else {
+                    current.1.abs_diff(next.1) + u8::from(current.0 != next.0)
+                };
+                assert_eq!(1, changed);
+                current = *next;
+            }
+            assert!(Counters.is_goal(&current));
+        }
+    }
+}
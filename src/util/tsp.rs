@@ -0,0 +1,215 @@
+use anyhow::{bail, Result};
+
+use crate::data::StringIdMap;
+
+// The dynamic programming table has 2^n * n entries
+pub const MAX_NODES: usize = 20;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Shape {
+    // Visits every node once, starting and ending anywhere
+    Path,
+    // Visits every node once and returns to the first
+    Cycle,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Objective {
+    Min,
+    Max,
+}
+
+impl Objective {
+    fn better(self, a: i64, b: i64) -> bool {
+        match self {
+            Objective::Min => a < b,
+            Objective::Max => a > b,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Tour {
+    pub total: i64,
+    // Node ids in visiting order, a cycle's closing edge back to the first is implied
+    pub order: Vec<usize>,
+}
+
+impl Tour {
+    pub fn named<'a>(&self, names: &'a StringIdMap) -> Vec<&'a str> {
+        self.order
+            .iter()
+            .map(|id| names.name(*id).map_or("?", |name| name.as_str()))
+            .collect()
+    }
+}
+
+// Held-Karp over weights[from][to], which needn't be symmetric. None marks a missing
+// edge, and the result is None if no tour uses only existing edges.
+pub fn held_karp(
+    weights: &[Vec<Option<i64>>],
+    shape: Shape,
+    objective: Objective,
+) -> Result<Option<Tour>> {
+    let n = weights.len();
+    if n > MAX_NODES {
+        bail!("Held-Karp is limited to {MAX_NODES} nodes, got {n}");
+    }
+    if weights.iter().any(|row| row.len() != n) {
+        bail!("Weights must be a square matrix");
+    }
+    if n <= 1 {
+        return Ok(Some(Tour {
+            total: 0,
+            order: (0..n).collect(),
+        }));
+    }
+    let full = (1 << n) - 1;
+    // Indexed by visited set * n + the last node visited
+    let mut best: Vec<Option<i64>> = vec![None; (full + 1) * n];
+    let mut previous: Vec<usize> = vec![0; (full + 1) * n];
+    // A cycle can start anywhere, so it might as well be node 0
+    let starts = match shape {
+        Shape::Path => n,
+        Shape::Cycle => 1,
+    };
+    for start in 0..starts {
+        best[(1 << start) * n + start] = Some(0);
+    }
+    for visited in 1..full {
+        for last in 0..n {
+            let Some(value) = best[visited * n + last] else {
+                continue;
+            };
+            for next in (0..n).filter(|next| visited & (1 << next) == 0) {
+                let Some(weight) = weights[last][next] else {
+                    continue;
+                };
+                let total = value + weight;
+                let slot = (visited | 1 << next) * n + next;
+                if best[slot].is_none_or(|current| objective.better(total, current)) {
+                    best[slot] = Some(total);
+                    previous[slot] = last;
+                }
+            }
+        }
+    }
+
+    let mut end: Option<(i64, usize)> = None;
+    for last in 0..n {
+        let Some(value) = best[full * n + last] else {
+            continue;
+        };
+        let total = match shape {
+            Shape::Path => value,
+            Shape::Cycle => match weights[last][0] {
+                Some(weight) => value + weight,
+                None => continue,
+            },
+        };
+        if end.is_none_or(|(current, _)| objective.better(total, current)) {
+            end = Some((total, last));
+        }
+    }
+    let Some((total, mut last)) = end else {
+        return Ok(None);
+    };
+    let mut order = Vec::with_capacity(n);
+    let mut visited = full;
+    loop {
+        order.push(last);
+        let before = previous[visited * n + last];
+        visited &= !(1 << last);
+        if visited == 0 {
+            break;
+        }
+        last = before;
+    }
+    order.reverse();
+    Ok(Some(Tour { total, order }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::random::generator;
+    use itertools::Itertools;
+
+    fn tour_total(weights: &[Vec<Option<i64>>], order: &[usize], shape: Shape) -> Option<i64> {
+        let mut edges: Vec<(usize, usize)> = order.iter().copied().tuple_windows().collect();
+        if shape == Shape::Cycle && order.len() > 1 {
+            edges.push((order[order.len() - 1], order[0]));
+        }
+        edges.iter().map(|(a, b)| weights[*a][*b]).sum()
+    }
+
+    fn brute_force(
+        weights: &[Vec<Option<i64>>],
+        shape: Shape,
+        objective: Objective,
+    ) -> Option<i64> {
+        let totals = (0..weights.len())
+            .permutations(weights.len())
+            .filter_map(|order| tour_total(weights, &order, shape));
+        match objective {
+            Objective::Min => totals.min(),
+            Objective::Max => totals.max(),
+        }
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        let mut random = generator(3);
+        let mut next = || random() as i64;
+        for n in 0..=6 {
+            for _ in 0..10 {
+                // Asymmetric, with some negative weights and some missing edges
+                let weights: Vec<Vec<Option<i64>>> = (0..n)
+                    .map(|_| {
+                        (0..n)
+                            .map(|_| {
+                                let value = next();
+                                (value % 5 != 0).then_some(value % 41 - 10)
+                            })
+                            .collect()
+                    })
+                    .collect();
+                for shape in [Shape::Path, Shape::Cycle] {
+                    for objective in [Objective::Min, Objective::Max] {
+                        let tour = held_karp(&weights, shape, objective).unwrap();
+                        let expected = if n == 1 {
+                            Some(0)
+                        } else {
+                            brute_force(&weights, shape, objective)
+                        };
+                        assert_eq!(expected, tour.as_ref().map(|t| t.total));
+                        if let Some(tour) = tour {
+                            assert_eq!(n, tour.order.iter().unique().count());
+                            if n > 1 {
+                                assert_eq!(
+                                    Some(tour.total),
+                                    tour_total(&weights, &tour.order, shape)
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_limits() {
+        let square = vec![vec![Some(1); MAX_NODES + 1]; MAX_NODES + 1];
+        assert!(held_karp(&square, Shape::Path, Objective::Min).is_err());
+        let ragged = vec![vec![Some(1); 2], vec![Some(1)]];
+        assert!(held_karp(&ragged, Shape::Path, Objective::Min).is_err());
+        let mut names = StringIdMap::default();
+        names.to_id("Alice");
+        let tour = Tour {
+            total: 0,
+            order: vec![0, 1],
+        };
+        assert_eq!(vec!["Alice", "?"], tour.named(&names));
+    }
+}
@@ -0,0 +1,361 @@
+use std::collections::{HashMap, VecDeque};
+
+use anyhow::{bail, Context, Result};
+use lazy_regex::regex_captures;
+
+use crate::data::StringIdMap;
+
+type WireId = usize;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Operand {
+    Wire(WireId),
+    Const(u16),
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Gate {
+    Value(Operand),
+    And(Operand, Operand),
+    Or(Operand, Operand),
+    Lshift(Operand, Operand),
+    Rshift(Operand, Operand),
+    Not(Operand),
+}
+
+impl Gate {
+    fn operands(&self) -> Vec<Operand> {
+        match *self {
+            Gate::Value(o) | Gate::Not(o) => vec![o],
+            Gate::And(lh, rh) | Gate::Or(lh, rh) | Gate::Lshift(lh, rh) | Gate::Rshift(lh, rh) => {
+                vec![lh, rh]
+            }
+        }
+    }
+
+    fn inputs(&self) -> Vec<WireId> {
+        self.operands()
+            .into_iter()
+            .filter_map(|o| match o {
+                Operand::Wire(id) => Some(id),
+                Operand::Const(_) => None,
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Circuit {
+    names: StringIdMap,
+    // Indexed by the wire driven by the gate, None for wires that are only ever read
+    gates: Vec<Option<Gate>>,
+    overrides: HashMap<WireId, u16>,
+}
+
+impl Circuit {
+    fn wire(&mut self, name: &str) -> WireId {
+        let id = self.names.to_id(name);
+        if id >= self.gates.len() {
+            self.gates.resize(id + 1, None);
+        }
+        id
+    }
+
+    fn operand(&mut self, name: &str, num: &str) -> Result<Operand> {
+        Ok(match (name.is_empty(), num.is_empty()) {
+            (false, false) => bail!("Indeterminate between Const and Ref"),
+            (false, true) => Operand::Wire(self.wire(name)),
+            (true, false) => Operand::Const(
+                num.parse()
+                    .with_context(|| format!("Couldn't convert operand '{num}' into u16"))?,
+            ),
+            (true, true) => bail!("Need a value to bind for the operand"),
+        })
+    }
+
+    fn add_line(&mut self, line: &str) -> Result<()> {
+        let (_, lh_name, lh_num, op, rh_name, rh_num, out) = regex_captures!(
+            "^([a-z]+)?([0-9]+)? ?(AND|OR|LSHIFT|RSHIFT|NOT)? ?([a-z]+)?([0-9]+)? -> ([a-z]+)$",
+            line
+        )
+        .with_context(|| format!("Failed to match line regex {line}"))?;
+        let gate = match op {
+            "" => Gate::Value(self.operand(lh_name, lh_num)?),
+            "NOT" => Gate::Not(self.operand(rh_name, rh_num)?),
+            _ => {
+                let lh = self.operand(lh_name, lh_num)?;
+                let rh = self.operand(rh_name, rh_num)?;
+                match op {
+                    "AND" => Gate::And(lh, rh),
+                    "OR" => Gate::Or(lh, rh),
+                    "LSHIFT" => Gate::Lshift(lh, rh),
+                    _ => Gate::Rshift(lh, rh),
+                }
+            }
+        };
+        let out = self.wire(out);
+        if self.gates[out].replace(gate).is_some() {
+            bail!("Wire '{}' is driven more than once", self.name(out));
+        }
+        Ok(())
+    }
+
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut circuit = Circuit::default();
+        for line in input.lines().filter(|l| !l.trim().is_empty()) {
+            circuit.add_line(line.trim())?;
+        }
+        Ok(circuit)
+    }
+
+    fn name(&self, id: WireId) -> &str {
+        self.names.name(id).map_or("?", |n| n.as_str())
+    }
+
+    fn id(&self, name: &str) -> Result<WireId> {
+        self.names
+            .get(name)
+            .with_context(|| format!("No wire named '{name}'"))
+    }
+
+    // The wire keeps the value, ignoring whatever gate drives it
+    pub fn set_override(&mut self, name: &str, value: u16) -> Result<()> {
+        let id = self.id(name)?;
+        self.overrides.insert(id, value);
+        Ok(())
+    }
+
+    fn inputs(&self, id: WireId) -> Result<Vec<WireId>> {
+        if self.overrides.contains_key(&id) {
+            return Ok(Vec::new());
+        }
+        match &self.gates[id] {
+            Some(gate) => Ok(gate.inputs()),
+            None => bail!("Wire '{}' is read but never driven", self.name(id)),
+        }
+    }
+
+    // Follows unevaluated inputs from a wire until one repeats
+    fn find_cycle(&self, start: WireId, pending: &[usize]) -> Result<Vec<WireId>> {
+        let mut path = vec![start];
+        loop {
+            let current = *path.last().unwrap_or(&start);
+            let next = self
+                .inputs(current)?
+                .into_iter()
+                .find(|input| pending[*input] > 0)
+                .with_context(|| "Unevaluated wire without pending inputs")?;
+            if let Some(position) = path.iter().position(|id| *id == next) {
+                let mut cycle = path.split_off(position);
+                cycle.push(next);
+                return Ok(cycle);
+            }
+            path.push(next);
+        }
+    }
+
+    // Kahn's algorithm, each wire comes after all the wires its gate reads
+    fn topological_order(&self) -> Result<Vec<WireId>> {
+        let count = self.gates.len();
+        let mut readers: Vec<Vec<WireId>> = vec![Vec::new(); count];
+        let mut pending = Vec::with_capacity(count);
+        for id in 0..count {
+            let inputs = self.inputs(id)?;
+            pending.push(inputs.len());
+            for input in inputs {
+                readers[input].push(id);
+            }
+        }
+        let mut ready: VecDeque<WireId> = (0..count).filter(|id| pending[*id] == 0).collect();
+        let mut order = Vec::with_capacity(count);
+        while let Some(id) = ready.pop_front() {
+            order.push(id);
+            for reader in &readers[id] {
+                pending[*reader] -= 1;
+                if pending[*reader] == 0 {
+                    ready.push_back(*reader);
+                }
+            }
+        }
+        if let Some(stuck) = (0..count).find(|id| pending[*id] > 0) {
+            let cycle = self.find_cycle(stuck, &pending)?;
+            let names: Vec<&str> = cycle.iter().rev().map(|id| self.name(*id)).collect();
+            bail!("Circuit has a cycle: {}", names.join(" -> "));
+        }
+        Ok(order)
+    }
+
+    // Signals on every wire, indexed like the wire ids
+    fn evaluate(&self) -> Result<Vec<u16>> {
+        let mut signals = vec![0; self.gates.len()];
+        for id in self.topological_order()? {
+            let value = |o: Operand| match o {
+                Operand::Wire(input) => signals[input],
+                Operand::Const(v) => v,
+            };
+            signals[id] = match (self.overrides.get(&id), self.gates[id]) {
+                (Some(v), _) => *v,
+                (None, Some(Gate::Value(o))) => value(o),
+                (None, Some(Gate::And(lh, rh))) => value(lh) & value(rh),
+                (None, Some(Gate::Or(lh, rh))) => value(lh) | value(rh),
+                (None, Some(Gate::Lshift(lh, rh))) => {
+                    value(lh).checked_shl(value(rh).into()).unwrap_or(0)
+                }
+                (None, Some(Gate::Rshift(lh, rh))) => {
+                    value(lh).checked_shr(value(rh).into()).unwrap_or(0)
+                }
+                (None, Some(Gate::Not(o))) => !value(o),
+                (None, None) => bail!("Wire '{}' is read but never driven", self.name(id)),
+            };
+        }
+        Ok(signals)
+    }
+
+    pub fn signal(&self, name: &str) -> Result<u16> {
+        let id = self.id(name)?;
+        Ok(self.evaluate()?[id])
+    }
+
+    fn operand_label(&self, operand: Operand) -> String {
+        match operand {
+            Operand::Wire(id) => self.name(id).to_string(),
+            Operand::Const(v) => v.to_string(),
+        }
+    }
+
+    // Graphviz, one node per wire labelled with its gate, edges run from inputs to outputs
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph circuit {\n");
+        for (id, gate) in self.gates.iter().enumerate() {
+            let name = self.name(id);
+            let label = match (self.overrides.get(&id), gate) {
+                (Some(v), _) => format!("{v} (override)"),
+                (None, Some(Gate::Value(o))) => self.operand_label(*o),
+                (None, Some(Gate::Not(o))) => format!("NOT {}", self.operand_label(*o)),
+                (None, Some(gate)) => {
+                    let op = match gate {
+                        Gate::And(..) => "AND",
+                        Gate::Or(..) => "OR",
+                        Gate::Lshift(..) => "LSHIFT",
+                        _ => "RSHIFT",
+                    };
+                    let operands = gate.operands();
+                    format!(
+                        "{} {op} {}",
+                        self.operand_label(operands[0]),
+                        self.operand_label(operands[1])
+                    )
+                }
+                (None, None) => "undriven".to_string(),
+            };
+            dot.push_str(&format!("  \"{name}\" [label=\"{name} = {label}\"];\n"));
+            if !self.overrides.contains_key(&id) {
+                for input in gate.iter().flat_map(|g| g.inputs()) {
+                    dot.push_str(&format!("  \"{}\" -> \"{name}\";\n", self.name(input)));
+                }
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "123 -> x
+        456 -> y
+        x AND y -> d
+        x OR y -> e
+        x LSHIFT 2 -> f
+        y RSHIFT 2 -> g
+        NOT x -> h
+        NOT y -> i";
+
+    #[test]
+    fn test_example() {
+        let circuit = Circuit::parse(EXAMPLE).unwrap();
+        let expected = [
+            ("d", 72),
+            ("e", 507),
+            ("f", 492),
+            ("g", 114),
+            ("h", 65412),
+            ("i", 65079),
+            ("x", 123),
+            ("y", 456),
+        ];
+        for (wire, signal) in expected {
+            assert_eq!(signal, circuit.signal(wire).unwrap(), "wire {wire}");
+        }
+        assert!(circuit.signal("z").is_err());
+    }
+
+    #[test]
+    fn test_override() {
+        let mut circuit = Circuit::parse(EXAMPLE).unwrap();
+        circuit.set_override("x", 1).unwrap();
+        assert_eq!(0, circuit.signal("d").unwrap());
+        assert_eq!(4, circuit.signal("f").unwrap());
+        assert!(circuit.set_override("z", 1).is_err());
+        // Overrides also break cycles and stand in for undriven wires
+        let mut circuit = Circuit::parse("a -> b\nb AND c -> a").unwrap();
+        circuit.set_override("a", 3).unwrap();
+        circuit.set_override("c", 7).unwrap();
+        assert_eq!(3, circuit.signal("b").unwrap());
+    }
+
+    #[test]
+    fn test_errors() {
+        let circuit = Circuit::parse("1 -> a\na AND d -> b\nb -> c\nc OR a -> d").unwrap();
+        let error = circuit.signal("a").unwrap_err().to_string();
+        assert_eq!("Circuit has a cycle: d -> b -> c -> d", error);
+        let circuit = Circuit::parse("x AND y -> d\n1 -> x").unwrap();
+        let error = circuit.signal("d").unwrap_err().to_string();
+        assert_eq!("Wire 'y' is read but never driven", error);
+        assert!(Circuit::parse("1 -> x\n2 -> x").is_err());
+        assert!(Circuit::parse("x XOR y -> z").is_err());
+    }
+
+    #[test]
+    fn test_deep_circuit() {
+        // Deep enough to overflow the stack when evaluated recursively
+        fn name(mut i: usize) -> String {
+            let mut s = String::new();
+            loop {
+                s.insert(0, (b'a' + (i % 26) as u8) as char);
+                i /= 26;
+                if i == 0 {
+                    return s;
+                }
+            }
+        }
+        let mut input = format!("1 -> {}\n", name(0));
+        for i in 1..100_000 {
+            input.push_str(&format!("NOT {} -> {}\n", name(i - 1), name(i)));
+        }
+        let circuit = Circuit::parse(&input).unwrap();
+        assert_eq!(65534, circuit.signal(&name(99_999)).unwrap());
+    }
+
+    #[test]
+    fn test_dot() {
+        let mut circuit = Circuit::parse("123 -> x\nx AND y -> d\nNOT x -> h").unwrap();
+        circuit.set_override("y", 5).unwrap();
+        assert_eq!(
+            vec![
+                "digraph circuit {",
+                "  \"x\" [label=\"x = 123\"];",
+                "  \"y\" [label=\"y = 5 (override)\"];",
+                "  \"d\" [label=\"d = x AND y\"];",
+                "  \"x\" -> \"d\";",
+                "  \"y\" -> \"d\";",
+                "  \"h\" [label=\"h = NOT x\"];",
+                "  \"x\" -> \"h\";",
+                "}",
+            ],
+            circuit.to_dot().lines().collect::<Vec<_>>()
+        );
+    }
+}
@@ -1,30 +1,32 @@
 use crate::util::input::get_input_string;
 
-use anyhow::Result;
-use lazy_regex::regex_is_match;
+use anyhow::{Context, Result};
 
 use crate::day::Day;
+use crate::util::hashmine::{leading_zeros, mine, Salted};
+
+// First numbers whose digest starts with five and six zeroes
+fn first_zeros(key: &str) -> Result<(usize, usize)> {
+    let salted = Salted::new(key);
+    let mut hashes = mine(1, |num| {
+        let zeros = leading_zeros(&salted.digest(num));
+        (zeros >= 5).then_some(zeros)
+    });
+    let (five_zeros, zeros) = hashes.next().with_context(|| "No five zero digest")?;
+    if zeros >= 6 {
+        return Ok((five_zeros, five_zeros));
+    }
+    let (six_zeros, _) = hashes
+        .find(|(_, zeros)| *zeros >= 6)
+        .with_context(|| "No six zero digest")?;
+    Ok((five_zeros, six_zeros))
+}
 
 pub struct Day04;
 
 impl Day for Day04 {
     fn main() -> Result<()> {
-        let input = get_input_string(2015, 04)?;
-        let input = input.trim();
-        let mut five_zeros: usize = 0;
-        let mut six_zeros: usize = 0;
-        let mut num: usize = 1;
-        while five_zeros == 0 || six_zeros == 0 {
-            let digest = md5::compute(format!("{input}{num}"));
-            let digest = format!("{digest:x}");
-            if five_zeros == 0 && regex_is_match!("^0{5}", &digest) {
-                five_zeros = num;
-            }
-            if six_zeros == 0 && regex_is_match!("^0{6}", &digest) {
-                six_zeros = num;
-            }
-            num += 1;
-        }
+        let (five_zeros, six_zeros) = first_zeros(&get_input_string(2015, 04)?)?;
         println!("First number with leading 5 zeroes md5 digest: {five_zeros}");
         println!("First number with leading 6 zeroes md5 digest: {six_zeros}");
         Ok(())
@@ -1,107 +1,76 @@
-use crate::util::input::get_input;
-use std::collections::HashMap;
-use std::io::{BufRead, BufReader};
+use crate::util::input::get_input_string;
 
 use anyhow::{bail, Context, Result};
 use lazy_regex::regex_captures;
 
 use crate::data::StringIdMap;
 use crate::day::Day;
-use crate::util::peek::Peek;
+use crate::util::tsp::{held_karp, Objective, Shape};
 
 pub struct Day09;
 
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
-struct Route {
-    a: usize,
-    b: usize,
-}
-
-impl Route {
-    const fn new(a: usize, b: usize) -> Self {
-        if a < b {
-            Route { a, b }
-        } else {
-            Route { a: b, b: a }
+// Distances between each pair of destinations, None where there's no direct route
+fn parse(input: &str) -> Result<(StringIdMap, Vec<Vec<Option<i64>>>)> {
+    let mut destinations = StringIdMap::default();
+    let mut routes = Vec::new();
+    for line in input.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+        let (_, a, b, length) = regex_captures!("^(.+) to (.+) = ([0-9]+)$", line)
+            .with_context(|| format!("Failed to match line regex {line}"))?;
+        let length: i64 = length.parse()?;
+        routes.push((destinations.to_id(a), destinations.to_id(b), length));
+    }
+    let mut distances = vec![vec![None; destinations.count()]; destinations.count()];
+    for (a, b, length) in routes {
+        if distances[a][b].replace(length).is_some() {
+            bail!(
+                "Unexpected duplicate route {} to {}",
+                destinations.name(a).map_or("?", |n| n.as_str()),
+                destinations.name(b).map_or("?", |n| n.as_str())
+            );
         }
+        distances[b][a] = Some(length);
     }
-}
-
-fn to_route(destinations: &mut StringIdMap, a: &str, b: &str) -> Route {
-    let a = destinations.to_id(a);
-    let b = destinations.to_id(b);
-    Route::new(a, b)
+    Ok((destinations, distances))
 }
 
 impl Day for Day09 {
     fn main() -> Result<()> {
-        let input = BufReader::new(get_input(2015, 09)?);
-        let mut destinations = StringIdMap::default();
-        let mut edges: HashMap<Route, usize> = HashMap::new();
-        let mut connections: HashMap<usize, Vec<usize>> = HashMap::new();
-        for line in input.lines().map(|l| l.unwrap()) {
-            let (_, a, b, length) = regex_captures!("(.+) to (.+) = ([0-9]+)", &line)
-                .with_context(|| format!("Failed to match line regex {line}"))?;
-            let length: usize = length.parse()?;
-            let route = to_route(&mut destinations, a, b);
-            let a = destinations.to_id(a);
-            let b = destinations.to_id(b);
-            if let Some(_) = edges.insert(route, length) {
-                bail!("Unexpected duplicate route {route:?}");
-            }
-            match connections.get_mut(&a) {
-                Some(conn) => {
-                    conn.push(b);
-                }
-                None => {
-                    connections.insert(a, vec![b]);
-                }
-            }
-            match connections.get_mut(&b) {
-                Some(conn) => {
-                    conn.push(a);
-                }
-                None => {
-                    connections.insert(b, vec![a]);
-                }
-            }
-        }
-        let mut paths: Vec<(Vec<usize>, usize)> = Vec::new();
-        for destination_id in 0..destinations.count() {
-            paths.push((vec![destination_id], 0))
+        let (destinations, distances) = parse(&get_input_string(2015, 09)?)?;
+        for (description, objective) in [("Shortest", Objective::Min), ("Longest", Objective::Max)]
+        {
+            let route = held_karp(&distances, Shape::Path, objective)?
+                .with_context(|| "No route visits every destination")?;
+            println!(
+                "{description} route: {} ({})",
+                route.total,
+                route.named(&destinations).join(" -> ")
+            );
         }
-        let mut shortest = usize::MAX;
-        let mut longest = usize::MIN;
-        while let Some((path, cost_so_far)) = paths.pop() {
-            let end = path
-                .peek()
-                .with_context(|| format!("Path should never be empty"))?;
-            if path.len() == destinations.count() {
-                if cost_so_far < shortest {
-                    shortest = cost_so_far;
-                }
-                if cost_so_far > longest {
-                    longest = cost_so_far;
-                }
-            }
-            for conn in connections
-                .get(end)
-                .with_context(|| format!("Connections should always exist"))?
-            {
-                if !path.contains(conn) {
-                    let mut new_path = path.clone();
-                    new_path.push(*conn);
-                    let route = Route::new(*end, *conn);
-                    let cost_so_far = cost_so_far
-                        + edges
-                            .get(&route)
-                            .with_context(|| format!("Edge should always exist"))?;
-                    paths.push((new_path, cost_so_far));
-                }
-            }
-        }
-        println!("Shortest route: {shortest}");
-        println!("Longest route: {longest}");
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example_day_2015_09() {
+        let (destinations, distances) =
+            parse("London to Dublin = 464\nLondon to Belfast = 518\nDublin to Belfast = 141")
+                .unwrap();
+        let shortest = held_karp(&distances, Shape::Path, Objective::Min)
+            .unwrap()
+            .unwrap();
+        assert_eq!(605, shortest.total);
+        let names = shortest.named(&destinations);
+        assert!(
+            names == ["London", "Dublin", "Belfast"] || names == ["Belfast", "Dublin", "London"]
+        );
+        let longest = held_karp(&distances, Shape::Path, Objective::Max)
+            .unwrap()
+            .unwrap();
+        assert_eq!(982, longest.total);
+        assert!(parse("A to B = 1\nB to A = 2").is_err());
+    }
+}
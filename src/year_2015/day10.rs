@@ -1,43 +1,190 @@
 use crate::util::input::get_input_string;
+use std::collections::{HashMap, HashSet};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 
+use crate::data::IdMap;
 use crate::day::Day;
 
+// How much of a sequence's start is followed when deciding whether it can be split off
+const PREFIX: usize = 16;
+
+// Sequences are ASCII digits
+fn look_and_say(sequence: &[u8]) -> Vec<u8> {
+    let mut next = Vec::with_capacity(sequence.len() * 2);
+    let mut j = 0;
+    while j < sequence.len() {
+        let digit = sequence[j];
+        let mark = j;
+        while j < sequence.len() && sequence[j] == digit {
+            j += 1;
+        }
+        next.extend((j - mark).to_string().bytes());
+        next.push(digit);
+    }
+    next
+}
+
+// Every generation of the left half ends with the same digit, so the halves evolve independently
+// for good as long as no generation of rest starts with that digit. Only a prefix of rest decides
+// how it starts, once that prefix repeats the answer can't change anymore.
+fn splits_before(last: u8, rest: &[u8]) -> bool {
+    let mut complete = rest.len() <= PREFIX;
+    let mut state = rest[..rest.len().min(PREFIX)].to_vec();
+    let mut seen = HashSet::new();
+    loop {
+        if state.first().is_none_or(|first| *first == last) {
+            return false;
+        }
+        if !seen.insert((state.clone(), complete)) {
+            return true;
+        }
+        let mut next = if complete {
+            look_and_say(&state)
+        } else {
+            // The last run of a truncated prefix may continue past it
+            let last_run = state.iter().rposition(|d| *d != state[state.len() - 1]);
+            look_and_say(&state[..last_run.map_or(0, |i| i + 1)])
+        };
+        complete = complete && next.len() <= PREFIX;
+        next.truncate(PREFIX);
+        state = next;
+    }
+}
+
+// Conway's audioactive elements, discovered by splitting sequences wherever the halves can
+// never interact again. Anything past the first couple of generations is built from 92 of them.
+#[derive(Default)]
+struct Elements {
+    names: IdMap<Vec<u8>>,
+    decays: HashMap<usize, Vec<usize>>,
+}
+
+impl Elements {
+    fn split(&mut self, sequence: &[u8]) -> Vec<usize> {
+        let mut ids = Vec::new();
+        let mut start = 0;
+        for i in 1..sequence.len() {
+            if sequence[i - 1] != sequence[i] && splits_before(sequence[i - 1], &sequence[i..]) {
+                ids.push(self.names.to_id(&sequence[start..i]));
+                start = i;
+            }
+        }
+        ids.push(self.names.to_id(&sequence[start..]));
+        ids
+    }
+
+    fn decay(&mut self, id: usize) -> Vec<usize> {
+        if let Some(products) = self.decays.get(&id) {
+            return products.clone();
+        }
+        let element = self.names.name(id).cloned().unwrap_or_default();
+        let products = self.split(&look_and_say(&element));
+        self.decays.insert(id, products.clone());
+        products
+    }
+
+    fn step(&mut self, counts: &HashMap<usize, u128>) -> Result<HashMap<usize, u128>> {
+        let mut next = HashMap::new();
+        for (id, count) in counts {
+            for product in self.decay(*id) {
+                let total: &mut u128 = next.entry(product).or_default();
+                *total = total
+                    .checked_add(*count)
+                    .with_context(|| "Too many elements")?;
+            }
+        }
+        Ok(next)
+    }
+
+    fn length(&self, counts: &HashMap<usize, u128>) -> Result<u128> {
+        counts.iter().try_fold(0u128, |total, (id, count)| {
+            let len = self.names.name(*id).map_or(0, |n| n.len()) as u128;
+            count
+                .checked_mul(len)
+                .and_then(|l| total.checked_add(l))
+                .with_context(|| "Sequence length overflows")
+        })
+    }
+}
+
+// Sequence length after 0..=iterations rounds
+fn lengths(seed: &str, iterations: usize) -> Result<Vec<u128>> {
+    let mut elements = Elements::default();
+    let mut counts: HashMap<usize, u128> = HashMap::new();
+    for id in elements.split(seed.as_bytes()) {
+        *counts.entry(id).or_default() += 1;
+    }
+    let mut lengths = vec![elements.length(&counts)?];
+    for _ in 0..iterations {
+        counts = elements.step(&counts)?;
+        lengths.push(elements.length(&counts)?);
+    }
+    Ok(lengths)
+}
+
 pub struct Day10;
 
 impl Day for Day10 {
     fn main() -> Result<()> {
-        let mut sequence: Vec<char> = get_input_string(2015, 10)?.trim().chars().collect();
-        let mut forty_len = 0;
-        let mut fifty_len = 0;
-        for i in 1..=50 {
-            let mut new_sequence = Vec::new();
-            {
-                // lock mutability temporarily for this loop sequence since I had a bug related to appending to the wrong sequenc
-                let sequence = sequence;
-                let mut j = 0;
-                while j < sequence.len() {
-                    let ch = sequence[j];
-                    let mark = j;
-                    while j < sequence.len() && sequence[j] == ch {
-                        j += 1;
-                    }
-                    for num_char in (j - mark).to_string().chars() {
-                        new_sequence.push(num_char);
-                    }
-                    new_sequence.push(ch);
-                }
-            }
-            sequence = new_sequence;
-            if i == 40 {
-                forty_len = sequence.len();
-            } else if i == 50 {
-                fifty_len = sequence.len();
+        let lengths = lengths(get_input_string(2015, 10)?.trim(), 100)?;
+        println!("Length @ 40th iteration: {}", lengths[40]);
+        println!("Length @ 50th iteration: {}", lengths[50]);
+        println!("Length @ 100th iteration: {}", lengths[100]);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example_day_2015_10() {
+        let mut sequence = b"1".to_vec();
+        for expected in ["11", "21", "1211", "111221", "312211"] {
+            sequence = look_and_say(&sequence);
+            assert_eq!(expected.as_bytes(), sequence);
+        }
+        assert_eq!(b"101".to_vec(), look_and_say(b"1111111111"));
+    }
+
+    #[test]
+    fn test_matches_naive() {
+        for seed in ["1", "1113222113", "3113322113", "22", "1111111111", "3333"] {
+            let mut sequence = seed.as_bytes().to_vec();
+            let lengths = lengths(seed, 30).unwrap();
+            for (i, length) in lengths.into_iter().enumerate() {
+                assert_eq!(sequence.len() as u128, length, "{seed} after {i}");
+                sequence = look_and_say(&sequence);
             }
         }
-        println!("Length @ 40th iteration: {forty_len}");
-        println!("Length @ 50th iteration: {fifty_len}");
-        Ok(())
+    }
+
+    #[test]
+    fn test_elements() {
+        let mut elements = Elements::default();
+        let mut counts: HashMap<usize, u128> = HashMap::new();
+        for id in elements.split(b"1113222113") {
+            *counts.entry(id).or_default() += 1;
+        }
+        for _ in 0..100 {
+            counts = elements.step(&counts).unwrap();
+        }
+        assert_eq!(92, counts.len());
+        let longest = counts.keys().filter_map(|id| elements.names.name(*id));
+        assert_eq!(Some(42), longest.map(|n| n.len()).max());
+        // Hydrogen decays into itself
+        let hydrogen = elements.names.to_id(b"22".as_slice());
+        assert_eq!(vec![hydrogen], elements.decay(hydrogen));
+    }
+
+    #[test]
+    fn test_large_iterations() {
+        let lengths = lengths("1113222113", 300).unwrap();
+        // Growth settles on Conway's constant
+        let ratio = lengths[300] as f64 / lengths[299] as f64;
+        assert!((ratio - 1.3035772690).abs() < 1e-6);
+        assert!(super::lengths("1113222113", 400).is_err());
     }
 }
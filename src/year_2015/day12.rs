@@ -1,71 +1,37 @@
 use crate::util::input::get_input_string;
-use std::iter::Sum;
+use std::env;
 
 use anyhow::Result;
 use serde_json::{self, Value};
 
 use crate::day::Day;
+use crate::util::json_query::{query, Filter};
 
-pub struct Day12;
-
-#[derive(Clone, Copy)]
-struct SometimesRed(i64, i64);
+// Filter to run instead of the puzzle's, printing the path of every number it includes,
+// like 'object:red,depth:3'
+const FILTER_VAR: &str = "AOC_FILTER";
 
-impl Sum for SometimesRed {
-    fn sum<I>(iter: I) -> Self
-    where
-        I: Iterator<Item = SometimesRed>,
-    {
-        let mut left = 0;
-        let mut right = 0;
-        for item in iter {
-            left += item.0;
-            right += item.1;
-        }
-        SometimesRed(left, right)
-    }
-}
-
-fn sum_numbers(value: &Value) -> SometimesRed {
-    match value {
-        Value::Number(n) => match n.as_i64() {
-            Some(i) => SometimesRed(i, i),
-            None => SometimesRed(0, 0),
-        },
-        Value::Array(vec) => vec.iter().map(|v| sum_numbers(v)).sum(),
-        Value::Object(map) => {
-            let mut has_red = false;
-            let mut left = 0;
-            let mut right = 0;
-            for (_, value) in map {
-                match value {
-                    Value::String(s) => {
-                        if s == "red" {
-                            has_red = true;
-                        }
-                    }
-                    _ => {
-                        let nums = sum_numbers(value);
-                        left += nums.0;
-                        right += nums.1;
-                    }
-                }
-            }
-            if has_red {
-                right = 0;
-            }
-            SometimesRed(left, right)
-        }
-        _ => SometimesRed(0, 0),
-    }
-}
+pub struct Day12;
 
 impl Day for Day12 {
     fn main() -> Result<()> {
-        let input = get_input_string(2015, 12)?;
-        let sums = sum_numbers(&serde_json::from_str(&input)?);
-        println!("Sum of all numbers: {}", sums.0);
-        println!("Sum of non-red numbers: {}", sums.1);
+        let document: Value = serde_json::from_str(&get_input_string(2015, 12)?)?;
+        if let Ok(filter) = env::var(FILTER_VAR) {
+            let summary = query(&document, &Filter::parse(&filter)?)?;
+            for (path, number) in &summary.numbers {
+                println!("{path} = {number}");
+            }
+            println!("Sum of {} numbers: {}", summary.count, summary.sum);
+            return Ok(());
+        }
+        let all = query(&document, &Filter::Any(Vec::new()))?;
+        println!("Sum of all numbers: {} ({} numbers)", all.sum, all.count);
+        let red = Filter::ObjectValue("red".to_string());
+        let non_red = query(&document, &red)?;
+        println!(
+            "Sum of non-red numbers: {} ({} numbers)",
+            non_red.sum, non_red.count
+        );
         Ok(())
     }
 }
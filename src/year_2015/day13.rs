@@ -1,79 +1,97 @@
-use crate::util::input::get_input;
-use std::collections::HashMap;
-use std::io::{BufRead, BufReader};
+use crate::util::input::get_input_string;
 
 use anyhow::{Context, Result};
 use lazy_regex::regex_captures;
 
 use crate::data::StringIdMap;
 use crate::day::Day;
+use crate::util::tsp::{held_karp, Objective, Shape, Tour};
 
 pub struct Day13;
 
-fn pair_happiness(happiness_chart: &HashMap<(usize, usize), i64>, a: usize, b: usize) -> i64 {
-    happiness_chart.get(&(a, b)).unwrap_or(&0) + happiness_chart.get(&(b, a)).unwrap_or(&0)
-}
-
-fn find_best_happiness(
-    people: &StringIdMap,
-    happiness_chart: &HashMap<(usize, usize), i64>,
-) -> i64 {
-    let mut sequences: Vec<Vec<usize>> = vec![Vec::new()];
-    for i in 0..people.count() {
-        let mut new_sequences = Vec::new();
-        for sequence in sequences {
-            for j in 0..=sequence.len() {
-                let mut s = sequence.clone();
-                s.insert(j, i);
-                new_sequences.push(s);
-            }
+// How much each person's happiness changes sitting next to each other person
+fn parse(input: &str) -> Result<(StringIdMap, Vec<Vec<i64>>)> {
+    let mut people = StringIdMap::default();
+    let mut changes = Vec::new();
+    for line in input.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+        let (_, person, sign, happiness, neighbor) = regex_captures!(
+            "^([A-Za-z]+) would (gain|lose) ([0-9]+) happiness units by sitting next to ([A-Za-z]+).?$",
+            line,
+        )
+        .with_context(|| format!("Could not match happiness meters '{line}'"))?;
+        let mut happiness: i64 = happiness.parse()?;
+        if sign == "lose" {
+            happiness *= -1;
         }
-        sequences = new_sequences
+        changes.push((people.to_id(person), people.to_id(neighbor), happiness));
     }
-    let mut best_happiness = i64::MIN;
-    for sequence in sequences {
-        let mut happiness = 0;
-        for i in 0..sequence.len() {
-            happiness += pair_happiness(
-                happiness_chart,
-                sequence[i],
-                sequence[(i + 1) % sequence.len()],
-            );
-        }
-        if happiness > best_happiness {
-            best_happiness = happiness;
-        }
+    let mut chart = vec![vec![0; people.count()]; people.count()];
+    for (person, neighbor, happiness) in changes {
+        chart[person][neighbor] = happiness;
     }
-    best_happiness
+    Ok((people, chart))
+}
+
+// Sitting next to each other changes both people's happiness
+fn best_seating(chart: &[Vec<i64>]) -> Result<Tour> {
+    let weights: Vec<Vec<Option<i64>>> = (0..chart.len())
+        .map(|a| {
+            (0..chart.len())
+                .map(|b| Some(chart[a][b] + chart[b][a]))
+                .collect()
+        })
+        .collect();
+    held_karp(&weights, Shape::Cycle, Objective::Max)?
+        .with_context(|| "Everyone can sit next to everyone, so there's always a seating")
 }
 
 impl Day for Day13 {
     fn main() -> Result<()> {
-        let input = BufReader::new(get_input(2015, 13)?);
-        let mut people = StringIdMap::default();
-        let mut happiness_chart: HashMap<(usize, usize), i64> = HashMap::new();
-        for line in input.lines().map(|l| l.unwrap()) {
-            let (_, person, sign, happiness, neighbor) = regex_captures!(
-                "([A-Za-z]+) would (gain|lose) ([0-9]+) happiness units by sitting next to ([A-Za-z]+)",
-                &line,
-            ).with_context(|| "Could not match happiness meters")?;
-            let person = people.to_id(person);
-            let neighbor = people.to_id(neighbor);
-            let mut happiness: i64 = happiness.parse()?;
-            if sign == "lose" {
-                happiness *= -1;
-            }
-            happiness_chart.insert((person, neighbor), happiness);
+        let (mut people, mut chart) = parse(&get_input_string(2015, 13)?)?;
+        let seating = best_seating(&chart)?;
+        println!("Best happiness without myself: {}", seating.total);
+        println!("  {}", seating.named(&people).join(", "));
+        // I'm indifferent to everyone, and they're indifferent to me
+        people.to_id("Myself");
+        for row in chart.iter_mut() {
+            row.push(0);
         }
-        println!(
-            "Best happiness without myself: {}",
-            find_best_happiness(&people, &happiness_chart)
-        );
-        people.to_id("---MYSELF---");
-        println!(
-            "Best happiness with myself: {}",
-            find_best_happiness(&people, &happiness_chart)
-        );
+        chart.push(vec![0; people.count()]);
+        let seating = best_seating(&chart)?;
+        println!("Best happiness with myself: {}", seating.total);
+        println!("  {}", seating.named(&people).join(", "));
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "Alice would gain 54 happiness units by sitting next to Bob.
+        Alice would lose 79 happiness units by sitting next to Carol.
+        Alice would lose 2 happiness units by sitting next to David.
+        Bob would gain 83 happiness units by sitting next to Alice.
+        Bob would lose 7 happiness units by sitting next to Carol.
+        Bob would lose 63 happiness units by sitting next to David.
+        Carol would lose 62 happiness units by sitting next to Alice.
+        Carol would gain 60 happiness units by sitting next to Bob.
+        Carol would gain 55 happiness units by sitting next to David.
+        David would gain 46 happiness units by sitting next to Alice.
+        David would lose 7 happiness units by sitting next to Bob.
+        David would gain 41 happiness units by sitting next to Carol.";
+
+    #[test]
+    fn example_day_2015_13() {
+        let (people, chart) = parse(EXAMPLE).unwrap();
+        let seating = best_seating(&chart).unwrap();
+        assert_eq!(330, seating.total);
+        let names = seating.named(&people);
+        assert_eq!(4, names.len());
+        // Alice sits between Bob and David
+        assert!(
+            names == ["Alice", "Bob", "Carol", "David"]
+                || names == ["Alice", "David", "Carol", "Bob"]
+        );
+    }
+}
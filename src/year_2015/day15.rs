@@ -1,115 +1,139 @@
-use crate::util::input::get_input;
-use std::io::{BufRead, BufReader};
-use std::ops::{Add, Mul};
+use crate::util::input::get_input_string;
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use itertools::Itertools;
 use lazy_regex::regex_captures;
 
 use crate::day::Day;
+use crate::util::composition::{best_composition, score, Exact};
+
+const TEASPOONS: u32 = 100;
+const CALORIES: i64 = 500;
 
 pub struct Day15;
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Default)]
 struct Ingredient {
-    capacity: i32,
-    durability: i32,
-    flavor: i32,
-    texture: i32,
-    calories: i32,
-}
-
-impl Mul<i32> for Ingredient {
-    type Output = Ingredient;
-
-    fn mul(self, other: i32) -> Ingredient {
-        Ingredient {
-            capacity: self.capacity * other,
-            durability: self.durability * other,
-            flavor: self.flavor * other,
-            texture: self.texture * other,
-            calories: self.calories * other,
-        }
-    }
+    name: String,
+    // Capacity, durability, flavor and texture
+    properties: Vec<i64>,
+    calories: i64,
 }
 
-impl Add<Ingredient> for Ingredient {
-    type Output = Ingredient;
-
-    fn add(self, other: Ingredient) -> Ingredient {
-        Ingredient {
-            capacity: self.capacity + other.capacity,
-            durability: self.durability + other.durability,
-            flavor: self.flavor + other.flavor,
-            texture: self.texture + other.texture,
-            calories: self.calories + other.calories,
-        }
+impl Ingredient {
+    fn from_line(line: &str) -> Result<Self> {
+        let (_, name, capacity, durability, flavor, texture, calories) = regex_captures!(
+            "^([A-Za-z]+): capacity ([-0-9]+), durability ([-0-9]+), flavor ([-0-9]+), texture ([-0-9]+), calories ([-0-9]+)$",
+            line,
+        )
+        .with_context(|| format!("Could not parse ingredient stats '{line}'"))?;
+        Ok(Ingredient {
+            name: name.to_string(),
+            properties: vec![
+                capacity.parse()?,
+                durability.parse()?,
+                flavor.parse()?,
+                texture.parse()?,
+            ],
+            calories: calories.parse()?,
+        })
     }
 }
 
-fn simple_score(cookie: Ingredient) -> i32 {
-    if cookie.capacity > 0 && cookie.durability > 0 && cookie.flavor > 0 && cookie.texture > 0 {
-        cookie.capacity * cookie.durability * cookie.flavor * cookie.texture
-    } else {
-        0
-    }
+// The best score and teaspoons of each ingredient, optionally with exactly some calories
+fn best_cookie(ingredients: &[Ingredient], calories: Option<i64>) -> Option<(i64, Vec<u32>)> {
+    let parts: Vec<Vec<i64>> = ingredients.iter().map(|i| i.properties.clone()).collect();
+    let per_unit: Vec<i64> = ingredients.iter().map(|i| i.calories).collect();
+    let exact = calories.map(|total| Exact {
+        per_unit: &per_unit,
+        total,
+    });
+    best_composition(&parts, TEASPOONS, exact)
 }
 
-fn calorie_score(cookie: Ingredient) -> i32 {
-    if cookie.calories == 500 {
-        simple_score(cookie)
-    } else {
-        0
+// Checks the recipe really scores what the search said, and describes it
+fn recipe(ingredients: &[Ingredient], best_score: i64, amounts: &[u32]) -> Result<String> {
+    let parts: Vec<Vec<i64>> = ingredients.iter().map(|i| i.properties.clone()).collect();
+    if score(&parts, amounts) != best_score {
+        bail!("Recipe {amounts:?} doesn't score {best_score}");
     }
+    let calories: i64 = ingredients
+        .iter()
+        .zip(amounts)
+        .map(|(i, amount)| i.calories * i64::from(*amount))
+        .sum();
+    let spoons = ingredients
+        .iter()
+        .zip(amounts)
+        .map(|(i, amount)| format!("{amount} {}", i.name))
+        .join(", ");
+    Ok(format!("{spoons} ({calories} calories)"))
 }
 
-fn best_cookie(
-    cookie: Ingredient,
-    ingredients: &[Ingredient],
-    teaspoons: i32,
-    score_fn: &dyn Fn(Ingredient) -> i32,
-) -> i32 {
-    if ingredients.len() < 1 {
-        score_fn(cookie)
-    } else if ingredients.len() == 1 {
-        score_fn(cookie + (ingredients[0] * teaspoons))
-    } else {
-        let mut best = 0;
-        for t in 0..=teaspoons {
-            let next = best_cookie(
-                cookie + (ingredients[0] * t),
-                &ingredients[1..],
-                teaspoons - t,
-                score_fn,
-            );
-            if next > best {
-                best = next;
+impl Day for Day15 {
+    fn main() -> Result<()> {
+        let input = get_input_string(2015, 15)?;
+        let ingredients: Vec<Ingredient> = input
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| Ingredient::from_line(l.trim()))
+            .collect::<Result<_>>()?;
+        for (description, calories) in [("ignorant", None), ("included", Some(CALORIES))] {
+            match best_cookie(&ingredients, calories) {
+                Some((best_score, amounts)) => {
+                    println!("Calorie {description} highest-scoring cookie: {best_score}");
+                    println!("  {}", recipe(&ingredients, best_score, &amounts)?);
+                }
+                None => println!("Calorie {description} cookies all score 0"),
             }
         }
-        best
+        Ok(())
     }
 }
 
-impl Day for Day15 {
-    fn main() -> Result<()> {
-        let input = BufReader::new(get_input(2015, 15)?);
-        let mut ingredients: Vec<Ingredient> = Vec::new();
-        for line in input.lines().map(|l| l.unwrap()) {
-            let (_, _name, capacity, durability, flavor, texture, calories) = regex_captures!(
-                "([A-Za-z]+): capacity ([-0-9]+), durability ([-0-9]+), flavor ([-0-9]+), texture ([-0-9]+), calories ([-0-9]+)",
-                &line,
-            ).with_context(|| "Could not parse ingredient stats")?;
-            ingredients.push(Ingredient {
-                capacity: capacity.parse()?,
-                durability: durability.parse()?,
-                flavor: flavor.parse()?,
-                texture: texture.parse()?,
-                calories: calories.parse()?,
-            })
-        }
-        let best_score = best_cookie(Ingredient::default(), &ingredients, 100, &simple_score);
-        println!("Calorie ignorant highest-scoring cookie: {best_score}");
-        let best_meal_score = best_cookie(Ingredient::default(), &ingredients, 100, &calorie_score);
-        println!("Calorie included highest-scoring cookie: {best_meal_score}");
-        Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example_day_2015_15() {
+        let ingredients: Vec<Ingredient> = [
+            "Butterscotch: capacity -1, durability -2, flavor 6, texture 3, calories 8",
+            "Cinnamon: capacity 2, durability 3, flavor -2, texture -1, calories 3",
+        ]
+        .iter()
+        .map(|l| Ingredient::from_line(l).unwrap())
+        .collect();
+        let (best_score, amounts) = best_cookie(&ingredients, None).unwrap();
+        assert_eq!(62842880, best_score);
+        assert_eq!(
+            "44 Butterscotch, 56 Cinnamon (520 calories)",
+            recipe(&ingredients, best_score, &amounts).unwrap()
+        );
+        let (best_score, amounts) = best_cookie(&ingredients, Some(CALORIES)).unwrap();
+        assert_eq!(57600000, best_score);
+        assert_eq!(vec![40, 60], amounts);
+        assert!(recipe(&ingredients, best_score + 1, &amounts).is_err());
+    }
+
+    #[test]
+    fn test_four_ingredients() {
+        let ingredients: Vec<Ingredient> = [
+            "Sprinkles: capacity 2, durability 0, flavor -2, texture 0, calories 3",
+            "Butterscotch: capacity 0, durability 5, flavor -3, texture 0, calories 3",
+            "Chocolate: capacity 0, durability 0, flavor 5, texture -1, calories 8",
+            "Candy: capacity 0, durability -1, flavor 0, texture 5, calories 8",
+        ]
+        .iter()
+        .map(|l| Ingredient::from_line(l).unwrap())
+        .collect();
+        let (best_score, amounts) = best_cookie(&ingredients, None).unwrap();
+        assert_eq!(21367368, best_score);
+        assert!(recipe(&ingredients, best_score, &amounts).is_ok());
+        let (best_score, amounts) = best_cookie(&ingredients, Some(CALORIES)).unwrap();
+        assert_eq!(1766400, best_score);
+        assert!(recipe(&ingredients, best_score, &amounts)
+            .unwrap()
+            .ends_with("(500 calories)"));
     }
 }
@@ -1,63 +1,48 @@
-use crate::util::input::get_input;
-use std::cmp::Reverse;
-use std::collections::{HashMap, HashSet};
-use std::io::{BufRead, BufReader};
+use crate::util::input::get_input_string;
+use std::env;
 
-use anyhow::{bail, Result};
-use lazy_regex::regex_captures;
+use anyhow::{bail, Context, Result};
 
 use crate::day::Day;
-use crate::util::priority_queue::PriorityQueue;
+use crate::year_2015::molecule::Grammar;
+
+// Set to only run the greedy search, which is quick but might not find the fewest steps
+const FAST_VAR: &str = "AOC_FAST";
+// Molecules the greedy search tries before giving up
+const GREEDY_BUDGET: usize = 100_000;
 
 pub struct Day19;
 
 impl Day for Day19 {
     fn main() -> Result<()> {
-        let input = BufReader::new(get_input(2015, 19)?);
-        let mut reverse_map: HashMap<String, String> = HashMap::new();
-        let mut medicine: String = String::new();
-        for line in input.lines().map(|l| l.unwrap()) {
-            if let Some((_, from, to)) = regex_captures!("([A-Za-z]+) => ([A-Za-z]+)", &line) {
-                if reverse_map.contains_key(to) {
-                    // Check assumption about only one way to reverse a 'long' string
-                    bail!("Unexpected existing reverse");
-                }
-                reverse_map.insert(to.to_string(), from.to_string());
-            } else if line != "" {
-                medicine = line;
-            }
+        let (grammar, medicine) = Grammar::parse(&get_input_string(2015, 19)?)?;
+        println!("Distinct molecules: {}", grammar.neighbors(&medicine).len());
+        let greedy = grammar.greedy_derivation(&medicine, GREEDY_BUDGET);
+        if let Some(steps) = &greedy {
+            grammar.replay(&medicine, steps)?;
         }
-        let mut possibilities: HashSet<String> = HashSet::new();
-        for (to, from) in &reverse_map {
-            for (index, _) in medicine.match_indices(from) {
-                let mut med = medicine.clone();
-                med.replace_range(index..index + from.len(), to);
-                possibilities.insert(med);
-            }
-        }
-        println!("Distinct molecules: {}", possibilities.len());
-        let mut ancestors = PriorityQueue::new();
-        let len = medicine.len();
-        ancestors.push((0, medicine), Reverse(len));
-        loop {
-            if let Some(((steps, ancestor), _)) = ancestors.pop() {
-                if ancestor == "e" {
-                    println!("Fewest steps to 'e': {steps}");
-                    break;
+        let steps = if env::var(FAST_VAR).is_ok() {
+            greedy.with_context(|| "Greedy search couldn't find steps to 'e'")?
+        } else {
+            let steps = grammar
+                .shortest_derivation(&medicine)
+                .with_context(|| "Could not find steps to 'e'")?;
+            grammar.replay(&medicine, &steps)?;
+            match greedy {
+                Some(greedy) if greedy.len() < steps.len() => {
+                    bail!("Greedy search beat the shortest derivation")
                 }
-
-                for (to, from) in &reverse_map {
-                    for (index, _) in ancestor.match_indices(to) {
-                        let mut anc = ancestor.clone();
-                        anc.replace_range(index..index + to.len(), from);
-                        let len = anc.len();
-                        ancestors.push((steps + 1, anc), Reverse(len));
-                    }
+                Some(greedy) if greedy.len() > steps.len() => {
+                    println!("Greedy search took {} steps", greedy.len())
                 }
-            } else {
-                println!("Could not find steps to 'e'");
-                break;
+                Some(_) => (),
+                None => println!("Greedy search gave up"),
             }
+            steps
+        };
+        println!("Fewest steps to 'e': {}", steps.len());
+        for step in steps {
+            println!("  {}", grammar.describe(step));
         }
         Ok(())
     }
@@ -1,58 +1,32 @@
 use crate::util::input::get_input_string;
 
-use anyhow::Result;
-use integer_sqrt::IntegerSquareRoot;
+use anyhow::{Context, Result};
 
 use crate::day::Day;
+use crate::util::math::divisor_sums;
 
 pub struct Day20;
 
-fn sum_of_divisors<F>(num: usize, filter: F) -> usize
+fn lowest_house<F>(presents: usize, filter: F, mult: usize) -> Option<usize>
 where
     F: Fn(usize, usize) -> bool,
 {
-    let mut n = num.integer_sqrt();
-    let mut sum = 0;
-    while n >= 1 {
-        if num % n == 0 {
-            if filter(num, n) {
-                sum += n;
-            }
-            let d = num / n;
-            if d != n && filter(num, d) {
-                sum += d;
-            }
-        }
-        n -= 1;
-    }
-    sum
-}
-
-fn present_count<F>(house_num: usize, filter: F, mult: usize) -> usize
-where
-    F: Fn(usize, usize) -> bool,
-{
-    sum_of_divisors(house_num, filter) * mult
+    // Every house gets at least mult * house_num presents, so this bounds the search
+    let limit = presents / mult + 1;
+    divisor_sums(limit, filter)
+        .iter()
+        .position(|sum| sum * mult >= presents)
 }
 
 impl Day for Day20 {
     fn main() -> Result<()> {
         let presents: usize = get_input_string(2015, 20)?.trim().parse()?;
-        let mut d1 = false;
-        let mut d2 = false;
-        for house_num in 1..=presents {
-            if !d1 && present_count(house_num, |_, _| true, 10) >= presents {
-                println!("lowest house number: {house_num}");
-                d1 = true;
-            }
-            if !d2 && present_count(house_num, |num, d| d * 50 >= num, 11) >= presents {
-                println!("new lowest house number: {house_num}");
-                d2 = true;
-            }
-            if d1 && d2 {
-                break;
-            }
-        }
+        let house_num =
+            lowest_house(presents, |_, _| true, 10).with_context(|| "Should find a house")?;
+        println!("lowest house number: {house_num}");
+        let house_num = lowest_house(presents, |num, d| d * 50 >= num, 11)
+            .with_context(|| "Should find a house")?;
+        println!("new lowest house number: {house_num}");
         Ok(())
     }
 }
@@ -85,11 +59,12 @@ mod tests {
             (19, 20),
             (20, 42),
         ];
+        let sums = divisor_sums(20, |_, _| true);
         for (num, expected) in data {
-            let actual = sum_of_divisors(num, |_, _| true);
+            let actual = sums[num];
             assert_eq!(
                 actual, expected,
-                "Got {}, but expected {} when calling sum_of_divisors({})",
+                "Got {}, but expected {} for the divisor sum of {}",
                 actual, expected, num
             );
         }
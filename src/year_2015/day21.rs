@@ -1,271 +1,330 @@
-use crate::util::input::get_input;
-use std::io::{BufRead, BufReader};
+use crate::util::input::get_input_string;
+use std::{env, fs};
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
+use itertools::Itertools;
 use lazy_regex::regex_captures;
 
 use crate::day::Day;
 
-pub struct Day21;
+// Path to a shop table to use instead of the built in one
+const SHOP_VAR: &str = "AOC_SHOP";
+
+const SHOP: &str = "\
+Weapons:    Cost  Damage  Armor
+Dagger        8     4       0
+Shortsword   10     5       0
+Warhammer    25     6       0
+Longsword    40     7       0
+Greataxe     74     8       0
+
+Armor:      Cost  Damage  Armor
+Leather      13     0       1
+Chainmail    31     0       2
+Splintmail   53     0       3
+Bandedmail   75     0       4
+Platemail   102     0       5
+
+Rings:      Cost  Damage  Armor
+Damage +1    25     1       0
+Damage +2    50     2       0
+Damage +3   100     3       0
+Defense +1   20     0       1
+Defense +2   40     0       2
+Defense +3   80     0       3
+";
+
+const PLAYER_HEALTH: u32 = 100;
 
-#[derive(Copy, Clone)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 struct Item {
-    armor: u32,
+    name: String,
     cost: u32,
     damage: u32,
+    armor: u32,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Shop {
+    // Each category's name and items, in the order they're listed
+    categories: Vec<(String, Vec<Item>)>,
+}
+
+impl Shop {
+    fn parse(input: &str) -> Result<Self> {
+        let mut shop = Shop::default();
+        for line in input.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+            if let Some((_, category)) = regex_captures!(r"^(\w+):\s+Cost\s+Damage\s+Armor$", line)
+            {
+                shop.categories.push((category.to_string(), Vec::new()));
+            } else if let Some((_, name, cost, damage, armor)) =
+                regex_captures!(r"^(\S.*?)\s+([0-9]+)\s+([0-9]+)\s+([0-9]+)$", line)
+            {
+                let (_, items) = shop
+                    .categories
+                    .last_mut()
+                    .with_context(|| format!("Item '{name}' comes before any category"))?;
+                items.push(Item {
+                    name: name.to_string(),
+                    cost: cost.parse()?,
+                    damage: damage.parse()?,
+                    armor: armor.parse()?,
+                });
+            } else {
+                bail!("Unrecognized shop line '{line}'");
+            }
+        }
+        Ok(shop)
+    }
+
+    fn items(&self, category: &str) -> Result<&[Item]> {
+        self.categories
+            .iter()
+            .find(|(name, _)| name == category)
+            .map(|(_, items)| items.as_slice())
+            .with_context(|| format!("The shop has no {category}"))
+    }
+}
+
+// How many distinct items of a category to buy
+#[derive(Copy, Clone, Debug)]
+struct Slot<'a> {
+    category: &'a str,
+    min: usize,
+    max: usize,
+}
+
+const SLOTS: [Slot; 3] = [
+    Slot {
+        category: "Weapons",
+        min: 1,
+        max: 1,
+    },
+    Slot {
+        category: "Armor",
+        min: 0,
+        max: 1,
+    },
+    Slot {
+        category: "Rings",
+        min: 0,
+        max: 2,
+    },
+];
+
+// Every set of items that fills each slot within its limits
+fn loadouts<'a>(shop: &'a Shop, slots: &[Slot]) -> Result<Vec<Vec<&'a Item>>> {
+    let mut choices = Vec::new();
+    for slot in slots {
+        let items = shop.items(slot.category)?;
+        let slot_choices: Vec<Vec<&Item>> = (slot.min..=slot.max)
+            .flat_map(|count| items.iter().combinations(count))
+            .collect();
+        choices.push(slot_choices);
+    }
+    Ok(choices
+        .into_iter()
+        .multi_cartesian_product()
+        .map(|chosen| chosen.concat())
+        .collect())
 }
 
-#[derive(Copy, Clone, Default)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
 struct Creature {
-    armor: u32,
-    damage: u32,
     health: u32,
+    damage: u32,
+    armor: u32,
 }
 
 impl Creature {
-    pub fn from_items(items: &[Item], health: u32) -> (Self, u32) {
-        let mut damage = 0;
-        let mut armor = 0;
-        let mut cost = 0;
-        for item in items {
-            armor += item.armor;
-            cost += item.cost;
-            damage += item.damage;
+    fn equipped(health: u32, items: &[&Item]) -> Self {
+        Creature {
+            health,
+            damage: items.iter().map(|item| item.damage).sum(),
+            armor: items.iter().map(|item| item.armor).sum(),
         }
-        (
-            Creature {
-                armor,
-                damage,
-                health,
-            },
-            cost,
-        )
     }
+
+    fn parse(input: &str) -> Result<Self> {
+        let mut health = None;
+        let mut damage = None;
+        let mut armor = None;
+        for line in input.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+            let Some((_, stat, value)) =
+                regex_captures!("^(Hit Points|Damage|Armor): ([0-9]+)$", line)
+            else {
+                bail!("Unrecognized line '{line}'");
+            };
+            let slot = match stat {
+                "Hit Points" => &mut health,
+                "Damage" => &mut damage,
+                _ => &mut armor,
+            };
+            *slot = Some(value.parse()?);
+        }
+        Ok(Creature {
+            health: health.with_context(|| "Boss has no hit points")?,
+            damage: damage.with_context(|| "Boss has no damage")?,
+            armor: armor.with_context(|| "Boss has no armor")?,
+        })
+    }
+
+    // Every attack does at least 1 damage
+    fn turns_to_kill(&self, target: &Creature) -> u32 {
+        let damage = self.damage.saturating_sub(target.armor).max(1);
+        target.health.div_ceil(damage)
+    }
+
+    // The player attacks first, so wins ties
+    fn beats(&self, boss: &Creature) -> bool {
+        self.turns_to_kill(boss) <= boss.turns_to_kill(self)
+    }
+}
+
+fn cost(items: &[&Item]) -> u32 {
+    items.iter().map(|item| item.cost).sum()
 }
 
-fn fight(player: Creature, boss: Creature) -> bool {
-    let mut player = player;
-    let mut boss = boss;
-    loop {
-        // player turn
-        let dmg = if player.damage > boss.armor {
-            player.damage - boss.armor
-        } else {
-            1
+fn names(items: &[&Item]) -> String {
+    items.iter().map(|item| &item.name).join(", ")
+}
+
+// The cheapest winning and most expensive losing loadouts
+fn extremes<'a>(
+    loadouts: &[Vec<&'a Item>],
+    boss: &Creature,
+) -> (Option<Vec<&'a Item>>, Option<Vec<&'a Item>>) {
+    let (wins, losses): (Vec<_>, Vec<_>) = loadouts
+        .iter()
+        .partition(|items| Creature::equipped(PLAYER_HEALTH, items).beats(boss));
+    let cheapest = wins.into_iter().min_by_key(|items| cost(items)).cloned();
+    let priciest = losses.into_iter().max_by_key(|items| cost(items)).cloned();
+    (cheapest, priciest)
+}
+
+pub struct Day21;
+
+impl Day for Day21 {
+    fn main() -> Result<()> {
+        let boss = Creature::parse(&get_input_string(2015, 21)?)?;
+        let shop = match env::var(SHOP_VAR) {
+            Ok(path) => Shop::parse(
+                &fs::read_to_string(&path).with_context(|| format!("Couldn't read '{path}'"))?,
+            )?,
+            Err(_) => Shop::parse(SHOP)?,
         };
-        if dmg >= boss.health {
-            return true;
+        let loadouts = loadouts(&shop, &SLOTS)?;
+        let (cheapest, priciest) = extremes(&loadouts, &boss);
+        match cheapest {
+            Some(items) => println!("Best winning cost: {} ({})", cost(&items), names(&items)),
+            None => println!("No loadout beats the boss"),
         }
-        boss.health -= dmg;
-        // boss turn
-        let dmg = if boss.damage > player.armor {
-            boss.damage - player.armor
-        } else {
-            1
-        };
-        if dmg >= player.health {
-            return false;
+        match priciest {
+            Some(items) => println!("Worst losing cost: {} ({})", cost(&items), names(&items)),
+            None => println!("No loadout loses to the boss"),
         }
-        player.health -= dmg;
+        Ok(())
     }
 }
 
-const PLAYER_HEALTH: u32 = 100;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-impl Day for Day21 {
-    fn main() -> Result<()> {
-        let input = BufReader::new(get_input(2015, 21)?);
-        let mut boss = Creature::default();
-        for line in input.lines().map(|l| l.unwrap()) {
-            if let Some((_, armor)) = regex_captures!("Armor: ([0-9]+)", &line) {
-                boss.armor = armor.parse()?;
+    // Plays the fight out turn by turn
+    fn simulate(player: Creature, boss: Creature) -> bool {
+        let (mut player_health, mut boss_health) = (player.health, boss.health);
+        loop {
+            boss_health =
+                boss_health.saturating_sub(player.damage.saturating_sub(boss.armor).max(1));
+            if boss_health == 0 {
+                return true;
             }
-            if let Some((_, damage)) = regex_captures!("Damage: ([0-9]+)", &line) {
-                boss.damage = damage.parse()?;
-            }
-            if let Some((_, health)) = regex_captures!("Hit Points: ([0-9]+)", &line) {
-                boss.health = health.parse()?;
+            player_health =
+                player_health.saturating_sub(boss.damage.saturating_sub(player.armor).max(1));
+            if player_health == 0 {
+                return false;
             }
         }
-        let weapons = [
-            Item {
-                cost: 8,
-                damage: 4,
-                armor: 0,
-            },
-            Item {
-                cost: 10,
-                damage: 5,
-                armor: 0,
-            },
-            Item {
-                cost: 25,
-                damage: 6,
-                armor: 0,
-            },
-            Item {
-                cost: 40,
-                damage: 7,
-                armor: 0,
-            },
-            Item {
-                cost: 74,
-                damage: 8,
-                armor: 0,
-            },
-        ];
-        let armors = [
-            Item {
-                cost: 13,
-                damage: 0,
-                armor: 1,
-            },
-            Item {
-                cost: 31,
-                damage: 0,
-                armor: 2,
-            },
-            Item {
-                cost: 53,
-                damage: 0,
-                armor: 3,
-            },
-            Item {
-                cost: 75,
-                damage: 0,
-                armor: 4,
-            },
-            Item {
-                cost: 102,
-                damage: 0,
-                armor: 5,
-            },
-        ];
-        let rings = [
-            Item {
-                cost: 25,
-                damage: 1,
-                armor: 0,
-            },
-            Item {
-                cost: 50,
-                damage: 2,
-                armor: 0,
-            },
-            Item {
-                cost: 100,
-                damage: 3,
-                armor: 0,
-            },
-            Item {
-                cost: 20,
-                damage: 0,
-                armor: 1,
-            },
-            Item {
-                cost: 40,
-                damage: 0,
-                armor: 2,
-            },
-            Item {
-                cost: 80,
-                damage: 0,
-                armor: 3,
-            },
-        ];
-        let mut best_cost = u32::MAX;
-        let mut worst_cost = 0;
-        for weapon in weapons {
-            // No Armor
-            // with 0 rings?
-            let (player, cost) = Creature::from_items(&[weapon], PLAYER_HEALTH);
-            if fight(player, boss) {
-                if cost < best_cost {
-                    best_cost = cost;
-                }
-            } else {
-                if cost > worst_cost {
-                    worst_cost = cost;
-                }
-            }
-            // with 1 ring?
-            for ring in rings {
-                let (player, cost) = Creature::from_items(&[weapon, ring], PLAYER_HEALTH);
-                if fight(player, boss) {
-                    if cost < best_cost {
-                        best_cost = cost;
-                    }
-                } else {
-                    if cost > worst_cost {
-                        worst_cost = cost;
-                    }
-                }
-            }
-            // with 2 rings?
-            for i in 0..rings.len() - 1 {
-                for j in i + 1..rings.len() {
-                    let (player, cost) =
-                        Creature::from_items(&[weapon, rings[i], rings[j]], PLAYER_HEALTH);
-                    if fight(player, boss) {
-                        if cost < best_cost {
-                            best_cost = cost;
-                        }
-                    } else {
-                        if cost > worst_cost {
-                            worst_cost = cost;
-                        }
-                    }
-                }
-            }
-            // Yes Armor
-            for armor in armors {
-                // with 0 rings?
-                let (player, cost) = Creature::from_items(&[weapon, armor], PLAYER_HEALTH);
-                if fight(player, boss) {
-                    if cost < best_cost {
-                        best_cost = cost;
-                    }
-                } else {
-                    if cost > worst_cost {
-                        worst_cost = cost;
-                    }
-                }
-                // with 1 ring?
-                for ring in rings {
-                    let (player, cost) =
-                        Creature::from_items(&[weapon, armor, ring], PLAYER_HEALTH);
-                    if fight(player, boss) {
-                        if cost < best_cost {
-                            best_cost = cost;
-                        }
-                    } else {
-                        if cost > worst_cost {
-                            worst_cost = cost;
-                        }
-                    }
-                }
-                // with 2 rings?
-                for i in 0..rings.len() - 1 {
-                    for j in i + 1..rings.len() {
-                        let (player, cost) = Creature::from_items(
-                            &[weapon, armor, rings[i], rings[j]],
-                            PLAYER_HEALTH,
-                        );
-                        if fight(player, boss) {
-                            if cost < best_cost {
-                                best_cost = cost;
-                            }
-                        } else {
-                            if cost > worst_cost {
-                                worst_cost = cost;
-                            }
-                        }
-                    }
+    }
+
+    #[test]
+    fn example_day_2015_21() {
+        let player = Creature {
+            health: 8,
+            damage: 5,
+            armor: 5,
+        };
+        let boss = Creature::parse("Hit Points: 12\nDamage: 7\nArmor: 2").unwrap();
+        assert_eq!(4, player.turns_to_kill(&boss));
+        assert_eq!(4, boss.turns_to_kill(&player));
+        assert!(player.beats(&boss));
+        let tougher = Creature { health: 13, ..boss };
+        assert!(!player.beats(&tougher));
+    }
+
+    #[test]
+    fn test_closed_form_matches_simulation() {
+        let boss = Creature {
+            health: 20,
+            damage: 5,
+            armor: 3,
+        };
+        for health in 1..30 {
+            for damage in 0..8 {
+                for armor in 0..8 {
+                    let player = Creature {
+                        health,
+                        damage,
+                        armor,
+                    };
+                    assert_eq!(simulate(player, boss), player.beats(&boss));
                 }
             }
         }
-        println!("Best winning cost: {best_cost}");
-        println!("Worst losing cost: {worst_cost}");
-        Ok(())
+    }
+
+    #[test]
+    fn test_loadouts() {
+        let shop = Shop::parse(SHOP).unwrap();
+        let all = loadouts(&shop, &SLOTS).unwrap();
+        // 5 weapons, no armor or one of 5, and no rings, one of 6 or two of 6
+        assert_eq!(5 * 6 * (1 + 6 + 15), all.len());
+        assert!(all
+            .iter()
+            .all(|items| items.iter().map(|item| &item.name).all_unique()));
+        assert!(loadouts(
+            &shop,
+            &[Slot {
+                category: "Shields",
+                min: 0,
+                max: 1
+            }]
+        )
+        .is_err());
+        assert!(Shop::parse("Dagger 8 4 0").is_err());
+
+        for (boss, best, worst) in [
+            ((100, 8, 2), 91, 158),
+            ((104, 8, 1), 78, 148),
+            ((109, 8, 2), 111, 188),
+            ((103, 9, 2), 121, 201),
+        ] {
+            let boss = Creature {
+                health: boss.0,
+                damage: boss.1,
+                armor: boss.2,
+            };
+            let (cheapest, priciest) = extremes(&all, &boss);
+            assert_eq!(Some(best), cheapest.as_deref().map(cost));
+            assert_eq!(Some(worst), priciest.as_deref().map(cost));
+        }
+        let boss = Creature {
+            health: 104,
+            damage: 8,
+            armor: 1,
+        };
+        let (cheapest, _) = extremes(&all, &boss);
+        assert_eq!("Longsword, Leather, Damage +1", names(&cheapest.unwrap()));
     }
 }
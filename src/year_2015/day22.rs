@@ -1,237 +1,521 @@
-use crate::util::input::get_input;
-use std::io::{BufRead, BufReader};
+use crate::util::input::get_input_string;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::env;
+use std::fmt;
+use std::str::FromStr;
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
+use itertools::Itertools;
 use lazy_regex::regex_captures;
 
 use crate::day::Day;
 use crate::util::priority_queue::PriorityQueue;
 
-pub struct Day22;
+// Comma separated spells to replay against the boss, instead of searching for the best
+const SPELLS_VAR: &str = "AOC_SPELLS";
+// Comma separated hit points lost at the start of each player turn in hard mode
+const DRAIN_VAR: &str = "AOC_DRAIN";
+const DEFAULT_DRAINS: [u32; 1] = [1];
+
+const PLAYER_HEALTH: u32 = 50;
+const PLAYER_MANA: u32 = 500;
+const MISSILE_DAMAGE: u32 = 4;
+const DRAIN_AMOUNT: u32 = 2;
+const SHIELD_ARMOR: u32 = 7;
+const POISON_DAMAGE: u32 = 3;
+const RECHARGE_MANA: u32 = 101;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+enum Spell {
+    MagicMissile,
+    Drain,
+    Shield,
+    Poison,
+    Recharge,
+}
+
+impl Spell {
+    const ALL: [Spell; 5] = [
+        Spell::MagicMissile,
+        Spell::Drain,
+        Spell::Shield,
+        Spell::Poison,
+        Spell::Recharge,
+    ];
+
+    fn cost(self) -> u32 {
+        match self {
+            Spell::MagicMissile => 53,
+            Spell::Drain => 73,
+            Spell::Shield => 113,
+            Spell::Poison => 173,
+            Spell::Recharge => 229,
+        }
+    }
+
+    // Turns the effect lasts, instant spells have none
+    fn duration(self) -> u8 {
+        match self {
+            Spell::MagicMissile | Spell::Drain => 0,
+            Spell::Shield | Spell::Poison => 6,
+            Spell::Recharge => 5,
+        }
+    }
+}
+
+impl fmt::Display for Spell {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Spell::MagicMissile => "Magic Missile",
+            Spell::Drain => "Drain",
+            Spell::Shield => "Shield",
+            Spell::Poison => "Poison",
+            Spell::Recharge => "Recharge",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Spell {
+    type Err = anyhow::Error;
+
+    // Case, spaces, dashes and underscores are ignored, so "magic-missile" works
+    fn from_str(s: &str) -> Result<Self> {
+        let name: String = s
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '_'))
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        Spell::ALL
+            .into_iter()
+            .find(|spell| spell.to_string().replace(' ', "").to_lowercase() == name)
+            .with_context(|| format!("Unknown spell '{}'", s.trim()))
+    }
+}
+
+fn parse_spells(list: &str) -> Result<Vec<Spell>> {
+    list.split(',')
+        .filter(|s| !s.trim().is_empty())
+        .map(Spell::from_str)
+        .collect()
+}
+
+// Where the battle log goes, () when nobody is reading it
+trait Log {
+    fn note(&mut self, line: impl FnOnce() -> String);
+}
+
+impl Log for () {
+    fn note(&mut self, _: impl FnOnce() -> String) {}
+}
+
+impl Log for Vec<String> {
+    fn note(&mut self, line: impl FnOnce() -> String) {
+        self.push(line());
+    }
+}
+
+enum Outcome {
+    Ongoing(Game),
+    Won,
+    Lost,
+}
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 struct Game {
-    boss_damage: u32,
-    boss_health: u32,
-    drain: u32,
-    mana_history: u32,
     player_health: u32,
     player_mana: u32,
+    boss_health: u32,
+    boss_damage: u32,
+    // Turns left on each effect
+    shield: u8,
     poison: u8,
     recharge: u8,
-    shielded: bool,
-    shield: u8,
+    // Hit points the player loses at the start of their turns
+    drain: u32,
 }
 
-const SHIELD_TURNS: u8 = 6;
-const POISON_TURNS: u8 = 6;
-const RECHARGE_TURNS: u8 = 5;
-
 impl Game {
-    fn new() -> Self {
+    fn new(boss_health: u32, boss_damage: u32) -> Self {
         Game {
-            boss_damage: 0,
-            boss_health: 0,
+            player_health: PLAYER_HEALTH,
+            player_mana: PLAYER_MANA,
+            boss_health,
+            boss_damage,
+            shield: 0,
+            poison: 0,
+            recharge: 0,
             drain: 0,
-            mana_history: 0,
-            player_health: 50,
-            player_mana: 500,
-            poison: POISON_TURNS,
-            recharge: RECHARGE_TURNS,
-            shielded: false,
-            shield: SHIELD_TURNS,
         }
     }
 
-    fn start_turn(&self) -> Self {
-        let mut game = *self;
-        if game.shield < SHIELD_TURNS {
-            game.shielded = true;
-            game.shield += 1;
-        }
-        if game.poison < POISON_TURNS {
-            game = game.damage_boss(3);
-            game.poison += 1;
-        }
-        if game.recharge < RECHARGE_TURNS {
-            game.player_mana += 101;
-            game.recharge += 1;
+    fn parse(input: &str) -> Result<Self> {
+        let mut health = None;
+        let mut damage = None;
+        for line in input.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+            if let Some((_, value)) = regex_captures!("^Hit Points: ([0-9]+)$", line) {
+                health = Some(value.parse()?);
+            } else if let Some((_, value)) = regex_captures!("^Damage: ([0-9]+)$", line) {
+                damage = Some(value.parse()?);
+            } else {
+                bail!("Unrecognized line '{line}'");
+            }
         }
-        game
+        Ok(Game::new(
+            health.with_context(|| "Boss has no hit points")?,
+            damage.with_context(|| "Boss has no damage")?,
+        ))
     }
 
-    fn end_turn(&self) -> Self {
-        let mut game = *self;
-        game.shielded = false;
-        game
+    fn with_drain(self, drain: u32) -> Self {
+        Game { drain, ..self }
     }
 
-    fn use_mana(&self, cost: u32) -> Option<Self> {
-        if self.player_mana >= cost {
-            let mut game = *self;
-            game.player_mana -= cost;
-            game.mana_history += cost;
-            Some(game)
+    fn armor(&self) -> u32 {
+        if self.shield > 0 {
+            SHIELD_ARMOR
         } else {
-            None
+            0
         }
     }
 
-    fn damage_boss(&self, dmg: u32) -> Self {
-        let mut game = *self;
-        game.boss_health = game.boss_health.saturating_sub(dmg);
-        game
+    fn timer(&mut self, spell: Spell) -> Option<&mut u8> {
+        match spell {
+            Spell::MagicMissile | Spell::Drain => None,
+            Spell::Shield => Some(&mut self.shield),
+            Spell::Poison => Some(&mut self.poison),
+            Spell::Recharge => Some(&mut self.recharge),
+        }
     }
 
-    fn damage_player(&self) -> Self {
-        let mut game = *self;
-        let dmg = if game.shielded {
-            if game.boss_damage <= 7 {
-                1
-            } else {
-                game.boss_damage - 7
-            }
-        } else {
-            game.boss_damage
-        };
-        game.player_health = game.player_health.saturating_sub(dmg);
-        game
+    fn describe(&self, whose: &str, log: &mut impl Log) {
+        log.note(|| format!("-- {whose} turn --"));
+        log.note(|| {
+            format!(
+                "- Player has {} hit points, {} armor, {} mana",
+                self.player_health,
+                self.armor(),
+                self.player_mana
+            )
+        });
+        log.note(|| format!("- Boss has {} hit points", self.boss_health));
     }
 
-    fn drain_health(&self) -> Self {
-        let mut game = *self;
-        game.player_health = game.player_health.saturating_sub(game.drain);
-        game
+    fn apply_effects(&mut self, log: &mut impl Log) {
+        if self.shield > 0 {
+            self.shield -= 1;
+            log.note(|| format!("Shield's timer is now {}.", self.shield));
+        }
+        if self.poison > 0 {
+            self.poison -= 1;
+            self.boss_health = self.boss_health.saturating_sub(POISON_DAMAGE);
+            log.note(|| {
+                format!(
+                    "Poison deals {POISON_DAMAGE} damage; its timer is now {}.",
+                    self.poison
+                )
+            });
+        }
+        if self.recharge > 0 {
+            self.recharge -= 1;
+            self.player_mana += RECHARGE_MANA;
+            log.note(|| {
+                format!(
+                    "Recharge provides {RECHARGE_MANA} mana; its timer is now {}.",
+                    self.recharge
+                )
+            });
+        }
     }
 
-    fn heal_player(&self, health: u32) -> Self {
+    // The start of the player's turn, up to the point they choose a spell
+    fn begin_turn(&self, log: &mut impl Log) -> Outcome {
         let mut game = *self;
-        game.player_health += health;
-        game
-    }
-
-    fn cast_magic_missile(&self) -> Option<Self> {
-        Some(self.use_mana(53)?.damage_boss(4))
+        game.describe("Player", log);
+        if game.drain > 0 {
+            game.player_health = game.player_health.saturating_sub(game.drain);
+            log.note(|| format!("Hard mode drains {} hit points.", game.drain));
+            if game.player_health == 0 {
+                log.note(|| "This kills the player, and the boss wins.".to_string());
+                return Outcome::Lost;
+            }
+        }
+        game.apply_effects(log);
+        if game.boss_health == 0 {
+            log.note(|| "This kills the boss, and the player wins.".to_string());
+            return Outcome::Won;
+        }
+        Outcome::Ongoing(game)
     }
 
-    fn cast_drain(&self) -> Option<Self> {
-        Some(self.use_mana(73)?.damage_boss(2).heal_player(2))
-    }
+    // Casts the spell, then plays out the boss's turn
+    fn cast(&self, spell: Spell, log: &mut impl Log) -> Result<Outcome> {
+        let mut game = *self;
+        if game.timer(spell).is_some_and(|timer| *timer > 0) {
+            bail!("{spell} is already active");
+        }
+        if game.player_mana < spell.cost() {
+            bail!(
+                "Can't afford {spell} ({} mana) with {} mana",
+                spell.cost(),
+                game.player_mana
+            );
+        }
+        game.player_mana -= spell.cost();
+        if let Some(timer) = game.timer(spell) {
+            *timer = spell.duration();
+        }
+        match spell {
+            Spell::MagicMissile => {
+                game.boss_health = game.boss_health.saturating_sub(MISSILE_DAMAGE);
+                log.note(|| format!("Player casts {spell}, dealing {MISSILE_DAMAGE} damage."));
+            }
+            Spell::Drain => {
+                game.boss_health = game.boss_health.saturating_sub(DRAIN_AMOUNT);
+                game.player_health += DRAIN_AMOUNT;
+                log.note(|| {
+                    format!("Player casts {spell}, dealing {DRAIN_AMOUNT} damage, and healing {DRAIN_AMOUNT} hit points.")
+                });
+            }
+            Spell::Shield | Spell::Poison | Spell::Recharge => {
+                log.note(|| format!("Player casts {spell}."));
+            }
+        }
+        if game.boss_health == 0 {
+            log.note(|| "This kills the boss, and the player wins.".to_string());
+            return Ok(Outcome::Won);
+        }
 
-    fn cast_shield(&self) -> Option<Self> {
-        if self.shield >= SHIELD_TURNS {
-            let mut game = self.use_mana(113)?;
-            game.shield = 0;
-            Some(game)
-        } else {
-            None
+        game.describe("Boss", log);
+        game.apply_effects(log);
+        if game.boss_health == 0 {
+            log.note(|| "This kills the boss, and the player wins.".to_string());
+            return Ok(Outcome::Won);
         }
+        let armor = game.armor();
+        let damage = game.boss_damage.saturating_sub(armor).max(1);
+        game.player_health = game.player_health.saturating_sub(damage);
+        log.note(|| match armor {
+            0 => format!("Boss attacks for {damage} damage."),
+            _ => format!(
+                "Boss attacks for {} - {armor} = {damage} damage.",
+                game.boss_damage
+            ),
+        });
+        if game.player_health == 0 {
+            log.note(|| "This kills the player, and the boss wins.".to_string());
+            return Ok(Outcome::Lost);
+        }
+        Ok(Outcome::Ongoing(game))
     }
+}
 
-    fn cast_poison(&self) -> Option<Self> {
-        if self.poison >= POISON_TURNS {
-            let mut game = self.use_mana(173)?;
-            game.poison = 0;
-            Some(game)
-        } else {
-            None
+// The mana and spells of the cheapest win, None if the boss can't be beaten
+fn cheapest_win(game: Game) -> Option<(u32, Vec<Spell>)> {
+    // Games at the start of a player turn, None once the boss is dead
+    let mut games: PriorityQueue<Reverse<u32>, (Option<Game>, Vec<Spell>)> = PriorityQueue::new();
+    games.push((Some(game), Vec::new()), Reverse(0));
+    let mut seen: HashSet<Game> = HashSet::new();
+    while let Some(((game, spells), Reverse(mana))) = games.pop() {
+        let Some(game) = game else {
+            return Some((mana, spells));
+        };
+        if !seen.insert(game) {
+            continue;
+        }
+        let game = match game.begin_turn(&mut ()) {
+            Outcome::Ongoing(game) => game,
+            Outcome::Won => return Some((mana, spells)),
+            Outcome::Lost => continue,
+        };
+        for spell in Spell::ALL {
+            let next = match game.cast(spell, &mut ()) {
+                Ok(Outcome::Ongoing(next)) => Some(next),
+                Ok(Outcome::Won) => None,
+                Ok(Outcome::Lost) | Err(_) => continue,
+            };
+            let mut spells = spells.clone();
+            spells.push(spell);
+            games.push((next, spells), Reverse(mana + spell.cost()));
         }
     }
+    None
+}
 
-    fn cast_recharge(&self) -> Option<Self> {
-        if self.recharge >= RECHARGE_TURNS {
-            let mut game = self.use_mana(229)?;
-            game.recharge = 0;
-            Some(game)
-        } else {
-            None
+// Plays the spells in order, returning the mana spent if they win the battle exactly as
+// they run out
+fn replay(game: Game, spells: &[Spell], log: &mut impl Log) -> Result<u32> {
+    let mut game = game;
+    let mut mana = 0;
+    for (round, spell) in (1..).zip(spells) {
+        let ready = match game.begin_turn(log) {
+            Outcome::Ongoing(game) => game,
+            Outcome::Won => bail!("The boss died before spell {round} ({spell}) was cast"),
+            Outcome::Lost => bail!("The player died before casting spell {round} ({spell})"),
+        };
+        mana += spell.cost();
+        match ready
+            .cast(*spell, log)
+            .with_context(|| format!("Spell {round} can't be cast"))?
+        {
+            Outcome::Ongoing(next) => game = next,
+            Outcome::Won if round == spells.len() => return Ok(mana),
+            Outcome::Won => bail!(
+                "The boss died after spell {round}, leaving {} unused",
+                spells.len() - round
+            ),
+            Outcome::Lost => bail!("The player died after spell {round} ({spell})"),
         }
     }
+    // Effects may still finish the boss off
+    match game.begin_turn(log) {
+        Outcome::Won => Ok(mana),
+        Outcome::Lost => bail!("The player died after the last spell"),
+        Outcome::Ongoing(game) => bail!(
+            "Ran out of spells with the boss on {} hit points",
+            game.boss_health
+        ),
+    }
 }
 
-fn find_best_mana_path(game: Game) -> u32 {
-    let mut games = PriorityQueue::new();
-    // Don't Reverse the mana_history value, late game first works better for calculating our results
-    games.push(game, game.mana_history);
-    let mut best_mana = u32::MAX;
-    while let Some((game, _)) = games.pop() {
-        if game.mana_history >= best_mana {
-            // Don't need to chase down worse options
-            continue;
-        }
-        let game = game.drain_health();
-        if game.player_health == 0 {
-            continue;
-        }
-        let game = game.start_turn(); // Start Player Turn
-        if game.boss_health == 0 {
-            if game.mana_history < best_mana {
-                best_mana = game.mana_history;
+pub struct Day22;
+
+impl Day for Day22 {
+    fn main() -> Result<()> {
+        let game = Game::parse(&get_input_string(2015, 22)?)?;
+        let drains = match env::var(DRAIN_VAR) {
+            Ok(drains) => drains
+                .split(',')
+                .map(|d| d.trim().parse())
+                .collect::<Result<Vec<u32>, _>>()
+                .with_context(|| format!("Bad {DRAIN_VAR} '{drains}'"))?,
+            Err(_) => DEFAULT_DRAINS.to_vec(),
+        };
+        let modes = [0].into_iter().chain(drains);
+        if let Ok(spells) = env::var(SPELLS_VAR) {
+            let spells = parse_spells(&spells)?;
+            for drain in modes {
+                let mut log = Vec::new();
+                let result = replay(game.with_drain(drain), &spells, &mut log);
+                println!("{}\n", log.join("\n"));
+                match result {
+                    Ok(mana) => println!("Spells win with drain {drain} for {mana} mana"),
+                    Err(error) => println!("Spells don't win with drain {drain}: {error:#}"),
+                }
             }
-            continue;
+            return Ok(());
         }
-        let possible_games = [
-            // Do Player Action
-            game.cast_magic_missile(),
-            game.cast_drain(),
-            game.cast_shield(),
-            game.cast_poison(),
-            game.cast_recharge(),
-        ];
-        for game in possible_games {
-            if let Some(game) = game {
-                if game.player_mana == 0 {
-                    continue;
-                }
-                if game.boss_health == 0 {
-                    if game.mana_history < best_mana {
-                        best_mana = game.mana_history;
-                    }
-                    continue;
-                }
-                let mut game = game.end_turn(); // End Player Turn
-                game = game.start_turn(); // Start Boss Turn
-                if game.boss_health == 0 {
-                    if game.mana_history < best_mana {
-                        best_mana = game.mana_history;
-                    }
-                    continue;
+        for drain in modes {
+            match cheapest_win(game.with_drain(drain)) {
+                Some((mana, spells)) => {
+                    println!("Best mana to win with drain {drain}: {mana}");
+                    println!("Spells: {}", spells.iter().join(", "));
                 }
-                game = game.damage_player();
-                if game.player_health == 0 {
-                    continue;
-                }
-                game = game.end_turn(); // End Boss Turn
-                games.push(game, game.mana_history);
+                None => println!("Kobayashi Maru with drain {drain}!"),
             }
         }
+        Ok(())
     }
-    best_mana
 }
 
-impl Day for Day22 {
-    fn main() -> Result<()> {
-        let input = BufReader::new(get_input(2015, 22)?);
-        let mut game = Game::new();
-        for line in input.lines().map(|l| l.unwrap()) {
-            if let Some((_, damage)) = regex_captures!("Damage: ([0-9]+)", &line) {
-                game.boss_damage = damage.parse()?;
-            }
-            if let Some((_, health)) = regex_captures!("Hit Points: ([0-9]+)", &line) {
-                game.boss_health = health.parse()?;
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_game(boss_health: u32) -> Game {
+        Game {
+            player_health: 10,
+            player_mana: 250,
+            ..Game::new(boss_health, 8)
         }
-        let best_mana = find_best_mana_path(game);
-        if best_mana == u32::MAX {
-            println!("Kobayashi Maru!");
+    }
+
+    #[test]
+    fn example_day_2015_22() {
+        let spells = [Spell::Poison, Spell::MagicMissile];
+        let mut log = Vec::new();
+        assert_eq!(226, replay(example_game(13), &spells, &mut log).unwrap());
+        assert_eq!(
+            vec![
+                "-- Player turn --",
+                "- Player has 10 hit points, 0 armor, 250 mana",
+                "- Boss has 13 hit points",
+                "Player casts Poison.",
+                "-- Boss turn --",
+                "- Player has 10 hit points, 0 armor, 77 mana",
+                "- Boss has 13 hit points",
+                "Poison deals 3 damage; its timer is now 5.",
+                "Boss attacks for 8 damage.",
+                "-- Player turn --",
+                "- Player has 2 hit points, 0 armor, 77 mana",
+                "- Boss has 10 hit points",
+                "Poison deals 3 damage; its timer is now 4.",
+                "Player casts Magic Missile, dealing 4 damage.",
+                "-- Boss turn --",
+                "- Player has 2 hit points, 0 armor, 24 mana",
+                "- Boss has 3 hit points",
+                "Poison deals 3 damage; its timer is now 3.",
+                "This kills the boss, and the player wins.",
+            ],
+            log
+        );
+        assert_eq!(Some((226, spells.to_vec())), cheapest_win(example_game(13)));
+
+        let spells = parse_spells("Recharge, shield, DRAIN, poison, magic-missile").unwrap();
+        let mut log = Vec::new();
+        assert_eq!(641, replay(example_game(14), &spells, &mut log).unwrap());
+        assert!(log.contains(&"Boss attacks for 8 - 7 = 1 damage.".to_string()));
+        assert!(log.contains(&"Recharge provides 101 mana; its timer is now 0.".to_string()));
+        assert_eq!(641, cheapest_win(example_game(14)).unwrap().0);
+    }
+
+    #[test]
+    fn test_cheapest_win() {
+        for (health, damage, expected) in [
+            (55, 8, [Some(953), Some(1289), Some(1309)]),
+            (51, 9, [Some(900), Some(1216), Some(1256)]),
+            (71, 10, [Some(1824), Some(1937), None]),
+        ] {
+            for (drain, expected) in (0..).zip(expected) {
+                let game = Game::new(health, damage).with_drain(drain);
+                let best = cheapest_win(game);
+                assert_eq!(expected, best.as_ref().map(|(mana, _)| *mana));
+                if let Some((mana, spells)) = best {
+                    assert_eq!(mana, replay(game, &spells, &mut ()).unwrap());
+                }
+            }
         }
-        println!("Best mana to win: {best_mana}");
-        game.drain = 1;
-        let best_mana = find_best_mana_path(game);
-        if best_mana == u32::MAX {
-            println!("Kobayashi Maru!");
+    }
+
+    #[test]
+    fn test_invalid_replays() {
+        let game = example_game(14);
+        for (spells, message) in [
+            (
+                "poison, poison",
+                "Spell 2 can't be cast: Poison is already active",
+            ),
+            (
+                "poison, recharge",
+                "Spell 2 can't be cast: Can't afford Recharge (229 mana) with 77 mana",
+            ),
+            ("poison", "Ran out of spells with the boss on 8 hit points"),
+            (
+                "poison, magic missile",
+                "The player died after spell 2 (Magic Missile)",
+            ),
+            ("drain, drain", "The player died after spell 2 (Drain)"),
+        ] {
+            let error = replay(game, &parse_spells(spells).unwrap(), &mut ()).unwrap_err();
+            assert_eq!(message, format!("{error:#}"));
         }
-        println!("Best mana to win with drain: {best_mana}");
-        Ok(())
+        let spells = parse_spells("poison, magic missile, drain").unwrap();
+        let error = replay(example_game(13), &spells, &mut ()).unwrap_err();
+        assert_eq!(
+            "The boss died after spell 2, leaving 1 unused",
+            error.to_string()
+        );
+        assert!(parse_spells("fireball").is_err());
     }
 }
@@ -1,10 +1,13 @@
 use crate::util::input::get_input;
-use std::io::{BufRead, BufReader};
+use std::fmt;
+use std::io::{self, BufRead, BufReader};
 
 use anyhow::{bail, Result};
 use lazy_regex::regex_captures;
 
 use crate::day::Day;
+use crate::util::debugger::{debug_script, Debugger, Decoded};
+use crate::util::register_machine::{self, Flow};
 
 #[derive(Copy, Clone)]
 enum Register {
@@ -20,33 +23,42 @@ impl Register {
             _ => bail!("Unexpected register {arg}"),
         }
     }
-}
 
-#[derive(Copy, Clone)]
-enum Offset {
-    Forward(usize),
-    Backward(usize),
+    fn index(self) -> usize {
+        match self {
+            Register::A => 0,
+            Register::B => 1,
+        }
+    }
 }
 
-impl Offset {
-    fn from_arg(sign: &str, arg: &str) -> Result<Self> {
-        let magnitude: usize = arg.parse()?;
-        match sign {
-            "+" => Ok(Offset::Forward(magnitude)),
-            "-" => Ok(Offset::Backward(magnitude)),
-            _ => bail!("Unexpected sign {sign}"),
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Register::A => write!(f, "a"),
+            Register::B => write!(f, "b"),
         }
     }
 }
 
+// Jump offsets are relative, written as +N or -N
+fn offset_from_arg(sign: &str, arg: &str) -> Result<i64> {
+    let magnitude: i64 = arg.parse()?;
+    match sign {
+        "+" => Ok(magnitude),
+        "-" => Ok(-magnitude),
+        _ => bail!("Unexpected sign {sign}"),
+    }
+}
+
 #[derive(Copy, Clone)]
 enum Instruction {
     Half(Register),
     Triple(Register),
     Increment(Register),
-    Jump(Offset),
-    JumpIfEven(Register, Offset),
-    JumpIfOne(Register, Offset),
+    Jump(i64),
+    JumpIfEven(Register, i64),
+    JumpIfOne(Register, i64),
 }
 
 impl Instruction {
@@ -61,19 +73,19 @@ impl Instruction {
             let register = Register::from_arg(reg)?;
             Ok(Instruction::Increment(register))
         } else if let Some((_, sign, off)) = regex_captures!("jmp ([+-]{1})([0-9]+)", &line) {
-            let offset = Offset::from_arg(sign, off)?;
+            let offset = offset_from_arg(sign, off)?;
             Ok(Instruction::Jump(offset))
         } else if let Some((_, reg, sign, off)) =
             regex_captures!("jie (a|b), ([+-]{1})([0-9]+)", &line)
         {
             let register = Register::from_arg(reg)?;
-            let offset = Offset::from_arg(sign, off)?;
+            let offset = offset_from_arg(sign, off)?;
             Ok(Instruction::JumpIfEven(register, offset))
         } else if let Some((_, reg, sign, off)) =
             regex_captures!("jio (a|b), ([+-]{1})([0-9]+)", &line)
         {
             let register = Register::from_arg(reg)?;
-            let offset = Offset::from_arg(sign, off)?;
+            let offset = offset_from_arg(sign, off)?;
             Ok(Instruction::JumpIfOne(register, offset))
         } else {
             bail!("Unrecognized instruction {line}")
@@ -81,96 +93,61 @@ impl Instruction {
     }
 }
 
-pub struct Day23;
+type Vm = register_machine::Vm<Instruction, u32, 2>;
 
-fn execute(program: &[Instruction], a: u32, b: u32) -> (u32, u32) {
-    let mut a = a;
-    let mut b = b;
-    let mut ins: usize = 0;
-    while ins < program.len() {
-        match program[ins] {
-            Instruction::Half(Register::A) => {
-                a /= 2;
-                ins += 1;
+impl register_machine::Instruction<u32, 2> for Instruction {
+    fn execute(self, vm: &mut Vm) -> Result<Flow> {
+        match self {
+            Instruction::Half(r) => vm.registers[r.index()] = vm.div(vm.registers[r.index()], 2)?,
+            Instruction::Triple(r) => {
+                vm.registers[r.index()] = vm.mul(vm.registers[r.index()], 3)?
             }
-            Instruction::Half(Register::B) => {
-                b /= 2;
-                ins += 1;
+            Instruction::Increment(r) => {
+                vm.registers[r.index()] = vm.add(vm.registers[r.index()], 1)?
             }
-            Instruction::Triple(Register::A) => {
-                a *= 3;
-                ins += 1;
+            Instruction::Jump(offset) => return Ok(Flow::Jump(offset)),
+            Instruction::JumpIfEven(r, offset) => {
+                if vm.registers[r.index()].is_multiple_of(2) {
+                    return Ok(Flow::Jump(offset));
+                }
             }
-            Instruction::Triple(Register::B) => {
-                b *= 3;
-                ins += 1;
-            }
-            Instruction::Increment(Register::A) => {
-                a += 1;
-                ins += 1;
-            }
-            Instruction::Increment(Register::B) => {
-                b += 1;
-                ins += 1;
-            }
-            Instruction::Jump(Offset::Forward(x)) => {
-                ins += x;
-            }
-            Instruction::Jump(Offset::Backward(x)) => {
-                ins = if x > ins {
-                    // Terminate Program
-                    program.len()
-                } else {
-                    ins - x
-                };
-            }
-            Instruction::JumpIfEven(register, offset) => {
-                let value = match register {
-                    Register::A => a,
-                    Register::B => b,
-                };
-                ins = if value % 2 == 0 {
-                    match offset {
-                        Offset::Forward(x) => ins + x,
-                        Offset::Backward(x) => {
-                            if x > ins {
-                                // Terminate Program
-                                program.len()
-                            } else {
-                                ins - x
-                            }
-                        }
-                    }
-                } else {
-                    ins + 1
-                };
-            }
-            Instruction::JumpIfOne(register, offset) => {
-                let value = match register {
-                    Register::A => a,
-                    Register::B => b,
-                };
-                ins = if value == 1 {
-                    match offset {
-                        Offset::Forward(x) => ins + x,
-                        Offset::Backward(x) => {
-                            if x > ins {
-                                // Terminate Program
-                                program.len()
-                            } else {
-                                ins - x
-                            }
-                        }
-                    }
-                } else {
-                    ins + 1
-                };
+            Instruction::JumpIfOne(r, offset) => {
+                if vm.registers[r.index()] == 1 {
+                    return Ok(Flow::Jump(offset));
+                }
             }
         }
+        Ok(Flow::Next)
+    }
+
+    fn decode(&self, index: usize) -> Decoded {
+        let (text, offset) = match self {
+            Instruction::Half(r) => (format!("hlf {r}"), None),
+            Instruction::Triple(r) => (format!("tpl {r}"), None),
+            Instruction::Increment(r) => (format!("inc {r}"), None),
+            Instruction::Jump(offset) => ("jmp".to_string(), Some(*offset)),
+            Instruction::JumpIfEven(r, offset) => (format!("jie {r},"), Some(*offset)),
+            Instruction::JumpIfOne(r, offset) => (format!("jio {r},"), Some(*offset)),
+        };
+        let jump = offset.map(|offset| index as i64 + offset);
+        Decoded { text, jump }
     }
-    (a, b)
 }
 
+fn machine(program: &[Instruction], a: u32, b: u32) -> Result<Vm> {
+    Vm::new(program)
+        .with_register('a', a)?
+        .with_register('b', b)
+}
+
+fn execute(program: &[Instruction], a: u32, b: u32) -> Result<(u32, u32)> {
+    let mut vm = machine(program, a, b)?;
+    vm.run(None)?;
+    Ok((vm.register('a')?, vm.register('b')?))
+}
+
+pub struct Day23;
+
 impl Day for Day23 {
     fn main() -> Result<()> {
         let input = BufReader::new(get_input(2015, 23)?);
@@ -178,10 +155,51 @@ impl Day for Day23 {
         for line in input.lines().map(|l| l.unwrap()) {
             program.push(Instruction::from_line(&line)?);
         }
-        let (_, b) = execute(&program, 0, 0);
+        if let Some(script) = debug_script() {
+            return Debugger::new(machine(&program, 0, 0)?, io::stdout()).run(&script);
+        }
+        let (_, b) = execute(&program, 0, 0)?;
         println!("Register b: {b}");
-        let (_, b) = execute(&program, 1, 0);
+        let (_, b) = execute(&program, 1, 0)?;
         println!("Register b when a=1: {b}");
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::debugger::disassemble;
+
+    fn parse(input: &str) -> Vec<Instruction> {
+        input
+            .lines()
+            .map(|l| Instruction::from_line(l).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn example_day_2015_23() {
+        let program = parse("inc a\njio a, +2\ntpl a\ninc a");
+        assert_eq!((2, 0), execute(&program, 0, 0).unwrap());
+        assert_eq!((10, 0), execute(&program, 2, 0).unwrap());
+        // Tripling past u32::MAX is an error rather than a panic
+        assert!(execute(&program, u32::MAX / 2, 0).is_err());
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let program = parse("jie b, +3\ninc b\njmp -2\nhlf a\njmp -5");
+        let listing = disassemble(&machine(&program, 0, 0).unwrap());
+        assert_eq!(
+            vec![
+                "L1:     0  jie b, L2",
+                "        1  inc b",
+                "        2  jmp L1",
+                "L2:     3  hlf a",
+                "        4  jmp halt",
+            ],
+            listing.lines().collect::<Vec<_>>()
+        );
+    }
+}
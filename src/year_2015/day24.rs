@@ -1,44 +1,236 @@
-use crate::util::input::get_input;
-use std::io::{BufRead, BufReader};
+use crate::util::input::get_input_string;
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use itertools::Itertools;
 
 use crate::day::Day;
 
 pub struct Day24;
 
-fn calculate_best_qe(weights: &[u64], compartments: u64) -> u64 {
-    let target_weight: u64 = weights.iter().sum::<u64>() / compartments;
-    let mut best: Option<(usize, u64)> = None;
-    for group in weights.iter().powerset() {
-        if let Some((best_len, _)) = best {
-            if group.len() > best_len {
+fn quantum_entanglement(group: &[u64]) -> Result<u128> {
+    group.iter().try_fold(1u128, |qe, weight| {
+        qe.checked_mul(*weight as u128)
+            .with_context(|| format!("Quantum entanglement of {group:?} overflows"))
+    })
+}
+
+// Depth first search for the passenger group of a given size, weights largest first
+struct GroupSearch<'a> {
+    weights: &'a [u64],
+    // Sum of each suffix of the weights
+    remaining: Vec<u64>,
+    compartments: usize,
+    target: u64,
+    size: usize,
+    chosen: Vec<usize>,
+    // Quantum entanglement of the best group so far, along with all the groups
+    best: Option<(u128, Vec<Vec<u64>>)>,
+}
+
+impl<'a> GroupSearch<'a> {
+    fn new(weights: &'a [u64], compartments: usize, target: u64) -> Self {
+        let mut remaining = vec![0; weights.len() + 1];
+        for i in (0..weights.len()).rev() {
+            remaining[i] = remaining[i + 1] + weights[i];
+        }
+        GroupSearch {
+            weights,
+            remaining,
+            compartments,
+            target,
+            size: 0,
+            chosen: Vec::new(),
+            best: None,
+        }
+    }
+
+    fn bound(&self) -> u128 {
+        self.best.as_ref().map_or(u128::MAX, |(qe, _)| *qe)
+    }
+
+    fn search(&mut self, start: usize, needed: u64, qe: u128) -> Result<()> {
+        if self.chosen.len() == self.size {
+            if needed == 0 {
+                self.check(qe);
+            }
+            return Ok(());
+        }
+        let left = self.size - self.chosen.len();
+        for i in start..self.weights.len() {
+            // Even all the weights left can't reach the target
+            if self.remaining[i] < needed || self.weights.len() - i < left {
                 break;
             }
+            let weight = self.weights[i];
+            if weight > needed {
+                continue;
+            }
+            // The rest of the picks are at most this weight
+            if weight.saturating_mul(left as u64) < needed {
+                break;
+            }
+            // Weights are at least 1, so quantum entanglement only grows
+            let qe = qe.checked_mul(weight as u128).with_context(|| {
+                format!(
+                    "Quantum entanglement of a {} package group overflows",
+                    self.size
+                )
+            })?;
+            if qe >= self.bound() {
+                continue;
+            }
+            self.chosen.push(i);
+            self.search(i + 1, needed - weight, qe)?;
+            self.chosen.pop();
+        }
+        Ok(())
+    }
+
+    // Keeps the group if the other packages can be split between the other compartments
+    fn check(&mut self, qe: u128) {
+        let first: Vec<u64> = self.chosen.iter().map(|i| self.weights[*i]).collect();
+        let rest: Vec<u64> = (0..self.weights.len())
+            .filter(|i| !self.chosen.contains(i))
+            .map(|i| self.weights[i])
+            .collect();
+        if let Some(others) = partition(&rest, self.compartments - 1, self.target) {
+            self.best = Some((qe, [vec![first], others].concat()));
+        }
+    }
+}
+
+// Splits weights into count groups that each sum to target, if possible
+fn partition(weights: &[u64], count: usize, target: u64) -> Option<Vec<Vec<u64>>> {
+    if weights.iter().sum::<u64>() != target * count as u64 {
+        return None;
+    }
+    let mut sorted = weights.to_vec();
+    sorted.sort_unstable_by(|a, b| b.cmp(a));
+    let mut groups = vec![Vec::new(); count];
+    let mut loads = vec![0; count];
+    fill(&sorted, target, &mut groups, &mut loads).then_some(groups)
+}
+
+fn fill(weights: &[u64], target: u64, groups: &mut [Vec<u64>], loads: &mut [u64]) -> bool {
+    let Some((weight, rest)) = weights.split_first() else {
+        return true;
+    };
+    for i in 0..groups.len() {
+        // Groups with the same load are interchangeable, so only try the first
+        if loads[i] + weight > target || loads[..i].contains(&loads[i]) {
+            continue;
         }
-        if group.iter().map(|v| *v).sum::<u64>() == target_weight {
-            let qe = group.iter().map(|v| *v).product();
-            best = Some(match best {
-                Some((best_len, best_qe)) => (best_len, if qe < best_qe { qe } else { best_qe }),
-                None => (group.len(), qe),
-            });
+        loads[i] += weight;
+        groups[i].push(*weight);
+        if fill(rest, target, groups, loads) {
+            return true;
+        }
+        loads[i] -= weight;
+        groups[i].pop();
+    }
+    false
+}
+
+// The compartment groups with the fewest packages, then the lowest quantum entanglement,
+// in the first group. None if the packages can't be balanced.
+fn balance(weights: &[u64], compartments: usize) -> Result<Option<Vec<Vec<u64>>>> {
+    if compartments == 0 {
+        bail!("Need at least one compartment");
+    }
+    if weights.contains(&0) {
+        bail!("Packages must weigh something");
+    }
+    let total: u64 = weights.iter().sum();
+    if !total.is_multiple_of(compartments as u64) {
+        return Ok(None);
+    }
+    let target = total / compartments as u64;
+    let mut sorted = weights.to_vec();
+    sorted.sort_unstable_by(|a, b| b.cmp(a));
+    let mut search = GroupSearch::new(&sorted, compartments, target);
+    for size in 1..=sorted.len() {
+        search.size = size;
+        search.search(0, target, 1)?;
+        if let Some((_, groups)) = search.best {
+            return Ok(Some(groups));
         }
     }
-    best.map(|(_, qe)| qe).unwrap_or(u64::MAX)
+    Ok(None)
 }
 
 impl Day for Day24 {
     fn main() -> Result<()> {
-        let input = BufReader::new(get_input(2015, 24)?);
-        let mut weights: Vec<u64> = Vec::new();
-        for line in input.lines().map(|l| l.unwrap()) {
-            weights.push(line.parse()?);
-        }
-        let best_qe = calculate_best_qe(&weights, 3);
-        println!("Best qe of 3 compartments: {best_qe}");
-        let best_qe = calculate_best_qe(&weights, 4);
-        println!("Best qe of 4 compartments: {best_qe}");
+        let input = get_input_string(2015, 24)?;
+        let weights: Vec<u64> = input
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| l.trim().parse())
+            .collect::<Result<_, _>>()?;
+        for compartments in [3, 4] {
+            let groups = balance(&weights, compartments)?
+                .with_context(|| format!("Can't balance {compartments} compartments"))?;
+            let best_qe = quantum_entanglement(&groups[0])?;
+            println!("Best qe of {compartments} compartments: {best_qe}");
+            for group in groups {
+                println!("  {}", group.iter().join(" "));
+            }
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn best_qe(weights: &[u64], compartments: usize) -> Option<u128> {
+        let groups = balance(weights, compartments).unwrap()?;
+        assert_eq!(compartments, groups.len());
+        let target = groups[0].iter().sum::<u64>();
+        assert!(groups.iter().all(|g| g.iter().sum::<u64>() == target));
+        let mut all: Vec<u64> = groups.concat();
+        let mut expected = weights.to_vec();
+        all.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(expected, all);
+        Some(quantum_entanglement(&groups[0]).unwrap())
+    }
+
+    #[test]
+    fn example_day_2015_24() {
+        let weights = [1, 2, 3, 4, 5, 7, 8, 9, 10, 11];
+        assert_eq!(Some(99), best_qe(&weights, 3));
+        assert_eq!(Some(44), best_qe(&weights, 4));
+        let groups = balance(&weights, 3).unwrap().unwrap();
+        assert_eq!(vec![11, 9], groups[0]);
+    }
+
+    #[test]
+    fn test_remainder_must_split() {
+        // 18 + 8 + 1 has the lowest qe of the three package groups, but 2, 4, 5, 12, 14
+        // and 17 can't be split into two groups of 27
+        let weights = [1, 2, 4, 5, 8, 12, 14, 17, 18];
+        assert_eq!(Some(168), best_qe(&weights, 3));
+        assert_eq!(None, best_qe(&[6, 4, 4, 4], 3));
+        assert_eq!(None, best_qe(&[1, 2, 4], 2));
+        assert!(balance(&[1, 0, 1], 2).is_err());
+    }
+
+    #[test]
+    fn test_large_quantum_entanglement() {
+        // Every group of two has a qe of 2^64, which doesn't fit a u64
+        assert_eq!(Some(1 << 64), best_qe(&[1 << 32; 6], 3));
+        // Groups of four would need 2^160
+        assert!(balance(&[1 << 40; 8], 2).is_err());
+    }
+
+    #[test]
+    fn test_puzzle_sized_input() {
+        let weights = [
+            1, 2, 3, 7, 11, 13, 17, 19, 23, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89,
+            97, 101, 103, 107, 109, 113,
+        ];
+        assert_eq!(Some(11846773891), best_qe(&weights, 3));
+        assert_eq!(Some(80393059), best_qe(&weights, 4));
+    }
+}
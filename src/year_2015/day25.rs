@@ -4,31 +4,20 @@ use anyhow::{Context, Result};
 use lazy_regex::regex_captures;
 
 use crate::day::Day;
+use crate::util::math::mod_pow;
 
-struct DiagonalIterator {
-    row: usize,
-    col: usize,
-}
+const FIRST_CODE: u64 = 20151125;
+const MULTIPLIER: u64 = 252533;
+const MODULUS: u64 = 33554393;
 
-impl DiagonalIterator {
-    fn new() -> Self {
-        DiagonalIterator { row: 1, col: 1 }
-    }
+// Codes are filled along diagonals, (1, 1) is the first code, (2, 1) the second, (1, 2) the third
+fn code_index(row: u64, col: u64) -> u64 {
+    let diagonal = row + col - 1;
+    diagonal * (diagonal - 1) / 2 + col - 1
 }
 
-impl Iterator for DiagonalIterator {
-    type Item = (usize, usize);
-
-    fn next(&mut self) -> Option<Self::Item> {
-        let res = (self.row, self.col);
-        self.row -= 1;
-        self.col += 1;
-        if self.row == 0 {
-            self.row = self.col;
-            self.col = 1;
-        }
-        Some(res)
-    }
+fn code_at(row: u64, col: u64) -> u64 {
+    FIRST_CODE * mod_pow(MULTIPLIER, code_index(row, col), MODULUS) % MODULUS
 }
 
 pub struct Day25;
@@ -39,18 +28,29 @@ impl Day for Day25 {
         let (_, target_row, target_col) =
             regex_captures!(".+?row ([0-9]+).+?column ([0-9]+)", &input)
                 .with_context(|| "Couldn't parse input")?;
-        let target: (usize, usize) = (target_row.parse()?, target_col.parse()?);
-        let mut code: u64 = 20151125;
-        let mut di = DiagonalIterator::new();
-        di.next();
-        for p in di {
-            code *= 252533;
-            code %= 33554393;
-            if p == target {
-                break;
-            }
-        }
+        let target: (u64, u64) = (target_row.parse()?, target_col.parse()?);
+        let code = code_at(target.0, target.1);
         println!("Code at {target:?} is {code}");
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example_day_2015_25() {
+        let data: Vec<((u64, u64), u64)> = vec![
+            ((1, 1), 20151125),
+            ((2, 1), 31916031),
+            ((1, 2), 18749137),
+            ((4, 3), 21345942),
+            ((6, 6), 27995004),
+            ((1, 6), 33511524),
+        ];
+        for ((row, col), expected) in data {
+            assert_eq!(expected, code_at(row, col));
+        }
+    }
+}
@@ -1,3 +1,4 @@
+mod circuit;
 mod day01;
 mod day02;
 mod day03;
@@ -23,6 +24,7 @@ mod day22;
 mod day23;
 mod day24;
 mod day25;
+mod molecule;
 
 pub use day01::Day01;
 pub use day02::Day02;
@@ -0,0 +1,451 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashSet};
+
+use anyhow::{bail, Context, Result};
+use lazy_regex::regex_captures;
+
+use crate::data::StringIdMap;
+use crate::util::priority_queue::PriorityQueue;
+
+// Every derivation starts from a single electron, which always gets the first id
+pub const ELECTRON: usize = 0;
+
+// Splits a molecule into element symbols, each an uppercase letter and any lowercase letters
+// after it. A lone 'e' is the electron.
+pub fn tokenize(molecule: &str) -> Result<Vec<&str>> {
+    if molecule == "e" {
+        return Ok(vec!["e"]);
+    }
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (index, c) in molecule.char_indices() {
+        if c.is_ascii_uppercase() {
+            if let Some(start) = start {
+                tokens.push(&molecule[start..index]);
+            }
+            start = Some(index);
+        } else if !c.is_ascii_lowercase() || start.is_none() {
+            bail!("Unexpected '{c}' at {index} in '{molecule}'");
+        }
+    }
+    if let Some(start) = start {
+        tokens.push(&molecule[start..]);
+    }
+    Ok(tokens)
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Rule {
+    pub from: usize,
+    pub to: Vec<usize>,
+}
+
+// Replaces the element at position using rule
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Step {
+    pub rule: usize,
+    pub position: usize,
+}
+
+// The symbols covering a span of the molecule, and how
+#[derive(Copy, Clone, Debug)]
+struct Derived {
+    steps: u32,
+    // The rule applied and where the span of its last symbol starts, None for the element itself
+    via: Option<(usize, usize)>,
+}
+
+// For each span of the molecule, the fewest steps for each symbol to become it, and for
+// the first few symbols of each rule to become it
+struct Chart {
+    len: usize,
+    complete: Vec<Vec<Option<Derived>>>,
+    // Keyed by (rule, symbols matched), valued by steps and where the last symbol's span starts
+    partial: Vec<BTreeMap<(usize, usize), (u32, usize)>>,
+}
+
+impl Chart {
+    fn span(&self, start: usize, end: usize) -> usize {
+        start * (self.len + 1) + end
+    }
+}
+
+fn improve<K: Ord>(map: &mut BTreeMap<K, (u32, usize)>, key: K, steps: u32, split: usize) {
+    if map.get(&key).is_none_or(|(current, _)| steps < *current) {
+        map.insert(key, (steps, split));
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Grammar {
+    pub symbols: StringIdMap,
+    pub rules: Vec<Rule>,
+}
+
+impl Grammar {
+    // The replacements, and the molecule after them
+    pub fn parse(input: &str) -> Result<(Self, Vec<usize>)> {
+        let mut symbols = StringIdMap::default();
+        symbols.to_id("e");
+        let mut rules = Vec::new();
+        let mut molecule = None;
+        for line in input.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+            if let Some((_, from, to)) = regex_captures!("^([A-Za-z]+) => ([A-Za-z]+)$", line) {
+                let from = match tokenize(from)?[..] {
+                    [symbol] => symbols.to_id(symbol),
+                    _ => bail!("Replacement '{line}' must start from a single element"),
+                };
+                let to = tokenize(to)?.iter().map(|s| symbols.to_id(*s)).collect();
+                rules.push(Rule { from, to });
+            } else if molecule.is_none() {
+                let tokens = tokenize(line)?;
+                molecule = Some(tokens.iter().map(|s| symbols.to_id(*s)).collect());
+            } else {
+                bail!("Unexpected line '{line}'");
+            }
+        }
+        let molecule = molecule.with_context(|| "No molecule after the replacements")?;
+        Ok((Grammar { symbols, rules }, molecule))
+    }
+
+    pub fn render(&self, molecule: &[usize]) -> String {
+        molecule
+            .iter()
+            .map(|id| self.symbols.name(*id).map_or("?", |name| name.as_str()))
+            .collect()
+    }
+
+    pub fn describe(&self, step: Step) -> String {
+        let rule = &self.rules[step.rule];
+        format!(
+            "{} => {} at {}",
+            self.render(&[rule.from]),
+            self.render(&rule.to),
+            step.position
+        )
+    }
+
+    pub fn apply(&self, molecule: &[usize], step: Step) -> Result<Vec<usize>> {
+        let rule = self
+            .rules
+            .get(step.rule)
+            .with_context(|| format!("No rule {}", step.rule))?;
+        if molecule.get(step.position) != Some(&rule.from) {
+            bail!(
+                "Can't replace {} in {}",
+                self.describe(step),
+                self.render(molecule)
+            );
+        }
+        let mut next = molecule[..step.position].to_vec();
+        next.extend_from_slice(&rule.to);
+        next.extend_from_slice(&molecule[step.position + 1..]);
+        Ok(next)
+    }
+
+    // Checks the steps take an electron to the molecule
+    pub fn replay(&self, molecule: &[usize], steps: &[Step]) -> Result<()> {
+        let mut current = vec![ELECTRON];
+        for step in steps {
+            current = self.apply(&current, *step)?;
+        }
+        if current != molecule {
+            bail!("The steps make {}", self.render(&current));
+        }
+        Ok(())
+    }
+
+    // Every molecule one replacement away
+    pub fn neighbors(&self, molecule: &[usize]) -> HashSet<Vec<usize>> {
+        let mut neighbors = HashSet::new();
+        for (position, symbol) in molecule.iter().enumerate() {
+            for (rule, _) in self
+                .rules
+                .iter()
+                .enumerate()
+                .filter(|(_, r)| r.from == *symbol)
+            {
+                if let Ok(next) = self.apply(molecule, Step { rule, position }) {
+                    neighbors.insert(next);
+                }
+            }
+        }
+        neighbors
+    }
+
+    // The fewest steps from an electron to the molecule, in the order they're applied. Fills
+    // a CYK style chart bottom up, with rules longer than two symbols matched a symbol at a
+    // time, so it works for any replacements. None if the molecule can't be made.
+    pub fn shortest_derivation(&self, molecule: &[usize]) -> Option<Vec<Step>> {
+        let len = molecule.len();
+        let symbols = self.symbols.count();
+        let mut chart = Chart {
+            len,
+            complete: vec![Vec::new(); (len + 1) * (len + 1)],
+            partial: vec![BTreeMap::new(); (len + 1) * (len + 1)],
+        };
+        for width in 1..=len {
+            for start in 0..=len - width {
+                let end = start + width;
+                let mut complete: Vec<Option<Derived>> = vec![None; symbols];
+                let mut partial = BTreeMap::new();
+                if width == 1 {
+                    complete[molecule[start]] = Some(Derived {
+                        steps: 0,
+                        via: None,
+                    });
+                }
+                for split in start + 1..end {
+                    for (&(rule, matched), &(steps, _)) in &chart.partial[chart.span(start, split)]
+                    {
+                        let to = &self.rules[rule].to;
+                        let Some(last) = chart.complete[chart.span(split, end)][to[matched]] else {
+                            continue;
+                        };
+                        let steps = steps + last.steps;
+                        if matched + 1 < to.len() {
+                            improve(&mut partial, (rule, matched + 1), steps, split);
+                        } else if complete[self.rules[rule].from]
+                            .is_none_or(|current| steps + 1 < current.steps)
+                        {
+                            complete[self.rules[rule].from] = Some(Derived {
+                                steps: steps + 1,
+                                via: Some((rule, split)),
+                            });
+                        }
+                    }
+                }
+                // Single symbol rules can chain within the span, but each link is a step
+                let mut changed = true;
+                while changed {
+                    changed = false;
+                    for (index, rule) in self.rules.iter().enumerate() {
+                        let (Some(inner), [_]) = (complete[rule.to[0]], &rule.to[..]) else {
+                            continue;
+                        };
+                        if complete[rule.from].is_none_or(|c| inner.steps + 1 < c.steps) {
+                            complete[rule.from] = Some(Derived {
+                                steps: inner.steps + 1,
+                                via: Some((index, start)),
+                            });
+                            changed = true;
+                        }
+                    }
+                }
+                for (index, rule) in self.rules.iter().enumerate() {
+                    if let (Some(first), true) = (complete[rule.to[0]], rule.to.len() > 1) {
+                        improve(&mut partial, (index, 1), first.steps, start);
+                    }
+                }
+                let span = chart.span(start, end);
+                chart.complete[span] = complete;
+                chart.partial[span] = partial;
+            }
+        }
+        let whole = chart.span(0, len);
+        chart.complete.get(whole)?.get(ELECTRON)?.as_ref()?;
+        let mut steps = Vec::new();
+        self.collect(&chart, (0, len), ELECTRON, &mut steps);
+        Some(steps)
+    }
+
+    // Adds the steps making the symbol into the span, parents before children and left to
+    // right, so everything before the span is already fully replaced
+    fn collect(&self, chart: &Chart, span: (usize, usize), symbol: usize, steps: &mut Vec<Step>) {
+        let (start, end) = span;
+        let Some(Derived {
+            via: Some((rule, split)),
+            ..
+        }) = chart.complete[chart.span(start, end)][symbol]
+        else {
+            return;
+        };
+        steps.push(Step {
+            rule,
+            position: start,
+        });
+        let to = &self.rules[rule].to;
+        let mut children = vec![(split, end, to[to.len() - 1])];
+        let (mut end, mut matched) = (split, to.len() - 1);
+        while matched > 0 {
+            let (_, split) = chart.partial[chart.span(start, end)][&(rule, matched)];
+            children.push((split, end, to[matched - 1]));
+            end = split;
+            matched -= 1;
+        }
+        for (start, end, symbol) in children.into_iter().rev() {
+            self.collect(chart, (start, end), symbol, steps);
+        }
+    }
+
+    // Works back from the molecule, always undoing a replacement in the shortest molecule
+    // seen so far. Quick on puzzle inputs, but it needn't find the fewest steps, so gives up
+    // after trying budget molecules.
+    pub fn greedy_derivation(&self, molecule: &[usize], budget: usize) -> Option<Vec<Step>> {
+        let mut queue = PriorityQueue::new();
+        let mut seen = HashSet::new();
+        queue.push((molecule.to_vec(), Vec::new()), Reverse(molecule.len()));
+        while let Some(((current, mut undone), _)) = queue.pop() {
+            if current == [ELECTRON] {
+                undone.reverse();
+                return Some(undone);
+            }
+            if seen.len() >= budget {
+                return None;
+            }
+            if !seen.insert(current.clone()) {
+                continue;
+            }
+            for (rule, Rule { from, to }) in self.rules.iter().enumerate() {
+                for position in 0..(current.len() + 1).saturating_sub(to.len()) {
+                    if !current[position..].starts_with(to) {
+                        continue;
+                    }
+                    let mut previous = current[..position].to_vec();
+                    previous.push(*from);
+                    previous.extend_from_slice(&current[position + to.len()..]);
+                    let mut steps = undone.clone();
+                    steps.push(Step { rule, position });
+                    let len = previous.len();
+                    queue.push((previous, steps), Reverse(len));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUZZLE_RULES: &str = "Al => ThF
+        Al => ThRnFAr
+        B => BCa
+        B => TiB
+        B => TiRnFAr
+        Ca => CaCa
+        Ca => PB
+        Ca => PRnFAr
+        Ca => SiRnFYFAr
+        Ca => SiRnMgAr
+        Ca => SiTh
+        F => CaF
+        F => PMg
+        F => SiAl
+        H => CRnAlAr
+        H => CRnFYFYFAr
+        H => CRnFYMgAr
+        H => CRnMgYFAr
+        H => HCa
+        H => NRnFYFAr
+        H => NRnMgAr
+        H => NTh
+        H => OB
+        H => ORnFAr
+        Mg => BF
+        Mg => TiMg
+        N => CRnFAr
+        N => HSi
+        O => CRnFYFAr
+        O => CRnMgAr
+        O => HP
+        O => NRnFAr
+        O => OTi
+        P => CaP
+        P => PTi
+        P => SiRnFAr
+        Si => CaSi
+        Th => ThCa
+        Ti => BP
+        Ti => TiTi
+        e => HF
+        e => NAl
+        e => OMg";
+
+    const HOHOHO: &str = "e => H
+        e => O
+        H => HO
+        H => OH
+        O => HH
+
+        HOHOHO";
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(vec!["Ca", "Rn", "Fe", "H"], tokenize("CaRnFeH").unwrap());
+        assert_eq!(vec!["e"], tokenize("e").unwrap());
+        assert!(tokenize("aB").is_err());
+        assert!(tokenize("Ca3").is_err());
+        assert!(Grammar::parse("CaRn => H\n\nH").is_err());
+        assert!(Grammar::parse("H => HO").is_err());
+    }
+
+    #[test]
+    fn test_shortest_derivation() {
+        let (grammar, molecule) = Grammar::parse(HOHOHO).unwrap();
+        assert_eq!(7, grammar.neighbors(&molecule).len());
+        let steps = grammar.shortest_derivation(&molecule).unwrap();
+        assert_eq!(6, steps.len());
+        grammar.replay(&molecule, &steps).unwrap();
+        let (_, hoh) = Grammar::parse("e => H\ne => O\nH => HO\nH => OH\nO => HH\nHOH").unwrap();
+        assert_eq!(3, grammar.shortest_derivation(&hoh).unwrap().len());
+        // Nothing makes C
+        let (grammar, molecule) = Grammar::parse("e => H\nH => HO\nHOC").unwrap();
+        assert_eq!(None, grammar.shortest_derivation(&molecule));
+        assert_eq!(None, grammar.greedy_derivation(&molecule, 100));
+    }
+
+    #[test]
+    fn test_unit_and_long_rules() {
+        // A chain of single element rules, and a rule too long for plain CYK
+        let (grammar, molecule) =
+            Grammar::parse("e => A\nA => B\nB => C\nC => DRnDAr\nD => C\nDRnDRnCArAr").unwrap();
+        let steps = grammar.shortest_derivation(&molecule).unwrap();
+        grammar.replay(&molecule, &steps).unwrap();
+        // e => A, A => B, B => C and C => DRnDAr, then D => C, C => DRnDAr and D => C inside
+        assert_eq!(7, steps.len());
+        assert_eq!("C => DRnDAr at 0", grammar.describe(steps[3]));
+        assert!(grammar
+            .replay(&molecule, &steps[..6])
+            .unwrap_err()
+            .to_string()
+            .starts_with("The steps make"));
+    }
+
+    #[test]
+    fn test_greedy_needs_checking() {
+        // Undoing R => AAAA makes the shortest molecule, but the chain back to e is longer
+        // than going through BB
+        let (grammar, molecule) =
+            Grammar::parse("e => P\nP => Q\nQ => R\nR => AAAA\ne => BB\nB => AA\nAAAA").unwrap();
+        let exact = grammar.shortest_derivation(&molecule).unwrap();
+        assert_eq!(3, exact.len());
+        grammar.replay(&molecule, &exact).unwrap();
+        let greedy = grammar.greedy_derivation(&molecule, 1000).unwrap();
+        assert_eq!(4, greedy.len());
+        grammar.replay(&molecule, &greedy).unwrap();
+        assert_eq!(None, grammar.greedy_derivation(&molecule, 1));
+    }
+
+    #[test]
+    fn test_puzzle_grammar() {
+        // Every puzzle rule adds one element, plus one for each Rn and Ar and two for each Y
+        let molecule = "CRnThCaSiThSiThRnCaFArArSiRnPBCaSiAlArBSiRnFArTiBCaPRnFArCaCaPTiBCa\
+            CaPRnCaCaSiThRnFArArCaSiThCaPTiTiTiBF";
+        let (grammar, molecule) = Grammar::parse(&format!("{PUZZLE_RULES}\n\n{molecule}")).unwrap();
+        let count = |symbol| {
+            let id = grammar.symbols.get(symbol);
+            molecule.iter().filter(|s| Some(**s) == id).count()
+        };
+        let expected = molecule.len() - count("Rn") - count("Ar") - 2 * count("Y") - 1;
+        assert_eq!(45, expected);
+        let steps = grammar.shortest_derivation(&molecule).unwrap();
+        assert_eq!(expected, steps.len());
+        grammar.replay(&molecule, &steps).unwrap();
+        // So every derivation takes the same number of steps, greedy included
+        let greedy = grammar.greedy_derivation(&molecule, 10_000).unwrap();
+        assert_eq!(expected, greedy.len());
+        grammar.replay(&molecule, &greedy).unwrap();
+    }
+}
@@ -1,3 +1,4 @@
+mod assembunny;
 mod day01;
 mod day02;
 mod day03;
@@ -14,15 +15,16 @@ mod day13;
 mod day14;
 mod day15;
 mod day16;
+mod decompress;
 // mod day17;
 // mod day18;
 // mod day19;
 // mod day20;
 // mod day21;
 // mod day22;
-// mod day23;
+mod day23;
 // mod day24;
-// mod day25;
+mod day25;
 
 pub use day01::Day01;
 pub use day02::Day02;
@@ -46,6 +48,6 @@ pub use day16::Day16;
 // pub use day20::Day20;
 // pub use day21::Day21;
 // pub use day22::Day22;
-// pub use day23::Day23;
+pub use day23::Day23;
 // pub use day24::Day24;
-// pub use day25::Day25;
+pub use day25::Day25;
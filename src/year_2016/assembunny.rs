@@ -0,0 +1,487 @@
+use std::fmt;
+
+use anyhow::{bail, Result};
+use lazy_regex::regex_captures;
+
+use crate::util::debugger::Decoded;
+use crate::util::register_machine::{self, Flow, Halt};
+
+pub const REGISTER_COUNT: usize = 4;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Operand {
+    Register(usize),
+    Literal(i64),
+}
+
+impl Operand {
+    fn from_arg(arg: &str) -> Result<Self> {
+        match arg {
+            "a" | "b" | "c" | "d" => Ok(Operand::Register((arg.as_bytes()[0] - b'a').into())),
+            _ => Ok(Operand::Literal(arg.parse()?)),
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operand::Register(r) => write!(f, "{}", (b'a' + *r as u8) as char),
+            Operand::Literal(v) => write!(f, "{v}"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Instruction {
+    Copy(Operand, Operand),
+    Increment(Operand),
+    Decrement(Operand),
+    JumpIfNotZero(Operand, Operand),
+    Toggle(Operand),
+    Out(Operand),
+}
+
+impl Instruction {
+    pub fn from_line(line: &str) -> Result<Self> {
+        let line = line.trim();
+        if let Some((_, x, y)) = regex_captures!("^cpy (-?[0-9]+|[a-d]) (-?[0-9]+|[a-d])$", line) {
+            Ok(Instruction::Copy(
+                Operand::from_arg(x)?,
+                Operand::from_arg(y)?,
+            ))
+        } else if let Some((_, x)) = regex_captures!("^inc ([a-d])$", line) {
+            Ok(Instruction::Increment(Operand::from_arg(x)?))
+        } else if let Some((_, x)) = regex_captures!("^dec ([a-d])$", line) {
+            Ok(Instruction::Decrement(Operand::from_arg(x)?))
+        } else if let Some((_, x, y)) =
+            regex_captures!("^jnz (-?[0-9]+|[a-d]) (-?[0-9]+|[a-d])$", line)
+        {
+            Ok(Instruction::JumpIfNotZero(
+                Operand::from_arg(x)?,
+                Operand::from_arg(y)?,
+            ))
+        } else if let Some((_, x)) = regex_captures!("^tgl (-?[0-9]+|[a-d])$", line) {
+            Ok(Instruction::Toggle(Operand::from_arg(x)?))
+        } else if let Some((_, x)) = regex_captures!("^out (-?[0-9]+|[a-d])$", line) {
+            Ok(Instruction::Out(Operand::from_arg(x)?))
+        } else {
+            bail!("Unrecognized instruction '{line}'")
+        }
+    }
+
+    fn toggle(self) -> Self {
+        match self {
+            Instruction::Increment(x) => Instruction::Decrement(x),
+            Instruction::Decrement(x) | Instruction::Toggle(x) | Instruction::Out(x) => {
+                Instruction::Increment(x)
+            }
+            Instruction::JumpIfNotZero(x, y) => Instruction::Copy(x, y),
+            Instruction::Copy(x, y) => Instruction::JumpIfNotZero(x, y),
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Instruction::Copy(x, y) => write!(f, "cpy {x} {y}"),
+            Instruction::Increment(x) => write!(f, "inc {x}"),
+            Instruction::Decrement(x) => write!(f, "dec {x}"),
+            Instruction::JumpIfNotZero(x, y) => write!(f, "jnz {x} {y}"),
+            Instruction::Toggle(x) => write!(f, "tgl {x}"),
+            Instruction::Out(x) => write!(f, "out {x}"),
+        }
+    }
+}
+
+// Loops recognized by optimize, each replaces the instructions starting at its index
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Fused {
+    // inc target, dec counter, jnz counter -2
+    Add {
+        target: usize,
+        counter: usize,
+    },
+    // cpy source inner, (add loop of inner into target), dec outer, jnz outer -5
+    Multiply {
+        target: usize,
+        source: Operand,
+        inner: usize,
+        outer: usize,
+    },
+}
+
+impl Fused {
+    pub fn len(&self) -> usize {
+        match self {
+            Fused::Add { .. } => 3,
+            Fused::Multiply { .. } => 6,
+        }
+    }
+}
+
+fn match_add(window: &[Instruction]) -> Option<(usize, usize)> {
+    use Instruction::*;
+    use Operand::*;
+    match window {
+        [Increment(Register(target)), Decrement(Register(counter)), JumpIfNotZero(Register(check), Literal(-2)), ..]
+        | [Decrement(Register(counter)), Increment(Register(target)), JumpIfNotZero(Register(check), Literal(-2)), ..]
+            if check == counter && target != counter =>
+        {
+            Some((*target, *counter))
+        }
+        _ => None,
+    }
+}
+
+fn match_multiply(window: &[Instruction]) -> Option<Fused> {
+    use Instruction::*;
+    use Operand::*;
+    match window {
+        [Copy(source, Register(inner)), add @ .., Decrement(Register(outer)), JumpIfNotZero(Register(check), Literal(-5))]
+            if add.len() == 3 && check == outer =>
+        {
+            let (target, counter) = match_add(add)?;
+            let distinct = counter == *inner && target != *outer && inner != outer;
+            let source_free = match source {
+                Register(r) => ![target, *inner, *outer].contains(r),
+                Literal(_) => true,
+            };
+            (distinct && source_free).then_some(Fused::Multiply {
+                target,
+                source: *source,
+                inner: *inner,
+                outer: *outer,
+            })
+        }
+        _ => None,
+    }
+}
+
+// Finds add and multiply loops, indexed by the position the loop starts at
+pub fn optimize(program: &[Instruction]) -> Vec<Option<Fused>> {
+    (0..program.len())
+        .map(|i| {
+            program.get(i..i + 6).and_then(match_multiply).or_else(|| {
+                program
+                    .get(i..i + 3)
+                    .and_then(match_add)
+                    .map(|(target, counter)| Fused::Add { target, counter })
+            })
+        })
+        .collect()
+}
+
+pub fn parse_program(input: &str) -> Result<Vec<Instruction>> {
+    input
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(Instruction::from_line)
+        .collect()
+}
+
+pub type Vm = register_machine::Vm<Instruction, i64, REGISTER_COUNT>;
+
+fn value(vm: &Vm, operand: Operand) -> i64 {
+    match operand {
+        Operand::Register(r) => vm.registers[r],
+        Operand::Literal(v) => v,
+    }
+}
+
+// Invalid instructions (produced by tgl) are skipped
+impl register_machine::Instruction<i64, REGISTER_COUNT> for Instruction {
+    fn execute(self, vm: &mut Vm) -> Result<Flow> {
+        match self {
+            Instruction::Copy(x, Operand::Register(r)) => {
+                vm.registers[r] = value(vm, x);
+            }
+            Instruction::Increment(Operand::Register(r)) => {
+                vm.registers[r] = vm.add(vm.registers[r], 1)?;
+            }
+            Instruction::Decrement(Operand::Register(r)) => {
+                vm.registers[r] = vm.sub(vm.registers[r], 1)?;
+            }
+            Instruction::JumpIfNotZero(x, y) => {
+                if value(vm, x) != 0 {
+                    return Ok(Flow::Jump(value(vm, y)));
+                }
+            }
+            Instruction::Toggle(x) => {
+                // Overflowing past either end is as out of range as any other target
+                let target = vm.pc.checked_add(value(vm, x)).and_then(|t| vm.index(t));
+                if let Some(target) = target {
+                    vm.program[target] = vm.program[target].toggle();
+                }
+            }
+            Instruction::Out(x) => {
+                vm.output.push(value(vm, x));
+            }
+            Instruction::Copy(_, Operand::Literal(_))
+            | Instruction::Increment(Operand::Literal(_))
+            | Instruction::Decrement(Operand::Literal(_)) => {}
+        }
+        Ok(Flow::Next)
+    }
+
+    fn decode(&self, index: usize) -> Decoded {
+        match self {
+            Instruction::JumpIfNotZero(x, Operand::Literal(offset)) => Decoded {
+                text: format!("jnz {x}"),
+                jump: Some(index as i64 + offset),
+            },
+            _ => Decoded {
+                text: self.to_string(),
+                jump: None,
+            },
+        }
+    }
+}
+
+// The shared VM plus the fused loops found by optimize
+#[derive(Clone, Debug)]
+pub struct Machine {
+    pub vm: Vm,
+    optimized: bool,
+    fused: Vec<Option<Fused>>,
+}
+
+impl Machine {
+    pub fn new(program: &[Instruction]) -> Self {
+        Machine {
+            vm: Vm::new(program),
+            optimized: false,
+            fused: Vec::new(),
+        }
+    }
+
+    pub fn optimized(program: &[Instruction]) -> Self {
+        let mut machine = Machine::new(program);
+        machine.optimized = true;
+        machine.fused = optimize(program);
+        machine
+    }
+
+    pub fn with_register(mut self, register: char, value: i64) -> Result<Self> {
+        self.vm = self.vm.with_register(register, value)?;
+        Ok(self)
+    }
+
+    pub fn register(&self, register: char) -> Result<i64> {
+        self.vm.register(register)
+    }
+
+    // Only runs the fused loop when it would terminate the same way the plain loop does
+    fn execute_fused(&mut self, fused: Fused) -> Result<bool> {
+        let vm = &mut self.vm;
+        match fused {
+            Fused::Add { target, counter } => {
+                if vm.registers[counter] <= 0 {
+                    return Ok(false);
+                }
+                vm.registers[target] = vm.add(vm.registers[target], vm.registers[counter])?;
+                vm.registers[counter] = 0;
+            }
+            Fused::Multiply {
+                target,
+                source,
+                inner,
+                outer,
+            } => {
+                let source = value(vm, source);
+                if source <= 0 || vm.registers[outer] <= 0 {
+                    return Ok(false);
+                }
+                let product = vm.mul(source, vm.registers[outer])?;
+                vm.registers[target] = vm.add(vm.registers[target], product)?;
+                vm.registers[inner] = 0;
+                vm.registers[outer] = 0;
+            }
+        }
+        Ok(true)
+    }
+
+    // Returns false once the program has halted
+    pub fn step(&mut self) -> Result<bool> {
+        let Some(index) = self.vm.index(self.vm.pc) else {
+            return Ok(false);
+        };
+        if let Some(fused) = self.fused.get(index).copied().flatten() {
+            if self.execute_fused(fused)? {
+                self.vm.pc += fused.len() as i64;
+                self.vm.steps += 1;
+                return Ok(true);
+            }
+        }
+        let toggles = matches!(self.vm.program[index], Instruction::Toggle(_));
+        self.vm.step()?;
+        if toggles && self.optimized {
+            // Toggled code may break (or create) a loop pattern
+            self.fused = optimize(&self.vm.program);
+        }
+        Ok(true)
+    }
+
+    pub fn run_until<F>(&mut self, stop: F, step_limit: Option<usize>) -> Result<Halt>
+    where
+        F: Fn(&Vm) -> bool,
+    {
+        loop {
+            if stop(&self.vm) {
+                return Ok(Halt::Stopped);
+            }
+            if step_limit.is_some_and(|limit| self.vm.steps >= limit) {
+                return Ok(Halt::StepLimit);
+            }
+            if !self.step()? {
+                return Ok(Halt::Finished);
+            }
+        }
+    }
+
+    pub fn run(&mut self, step_limit: Option<usize>) -> Result<Halt> {
+        self.run_until(|_| false, step_limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::debugger::disassemble;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Instruction::Copy(Operand::Literal(-41), Operand::Register(0)),
+            Instruction::from_line("cpy -41 a").unwrap()
+        );
+        assert_eq!(
+            Instruction::JumpIfNotZero(Operand::Register(2), Operand::Register(3)),
+            Instruction::from_line("jnz c d").unwrap()
+        );
+        assert!(Instruction::from_line("inc 3").is_err());
+        assert!(Instruction::from_line("cpy a e").is_err());
+        assert!(Instruction::from_line("mul a b").is_err());
+    }
+
+    #[test]
+    fn test_backward_jump() {
+        let program = parse_program("cpy 3 b\ninc a\ndec b\njnz b -2").unwrap();
+        let mut machine = Machine::new(&program);
+        assert_eq!(Halt::Finished, machine.run(None).unwrap());
+        assert_eq!(3, machine.register('a').unwrap());
+    }
+
+    #[test]
+    fn test_optimize() {
+        let program = parse_program(
+            "cpy b c\ninc a\ndec c\njnz c -2\ndec d\njnz d -5\ndec b\ninc a\njnz b -2",
+        )
+        .unwrap();
+        let fused = optimize(&program);
+        assert_eq!(
+            Some(Fused::Multiply {
+                target: 0,
+                source: Operand::Register(1),
+                inner: 2,
+                outer: 3
+            }),
+            fused[0]
+        );
+        assert_eq!(
+            Some(Fused::Add {
+                target: 0,
+                counter: 2
+            }),
+            fused[1]
+        );
+        assert_eq!(
+            Some(Fused::Add {
+                target: 0,
+                counter: 1
+            }),
+            fused[6]
+        );
+        assert_eq!(3, fused.iter().flatten().count());
+        let mut plain = Machine::new(&program)
+            .with_register('b', 7)
+            .unwrap()
+            .with_register('d', 6)
+            .unwrap();
+        let mut optimized = Machine::optimized(&program)
+            .with_register('b', 7)
+            .unwrap()
+            .with_register('d', 6)
+            .unwrap();
+        plain.run(None).unwrap();
+        optimized.run(None).unwrap();
+        assert_eq!(49, plain.register('a').unwrap());
+        assert_eq!(plain.vm.registers, optimized.vm.registers);
+        assert_eq!(2, optimized.vm.steps);
+    }
+
+    #[test]
+    fn test_optimize_guards() {
+        // Zero counters must not be fused, the plain loop runs into negatives
+        let program = parse_program("inc a\ndec b\njnz b -2").unwrap();
+        let mut machine = Machine::optimized(&program).with_register('b', 0).unwrap();
+        assert_eq!(Halt::StepLimit, machine.run(Some(30)).unwrap());
+        assert_eq!(10, machine.register('a').unwrap());
+        // Toggling inside a fused loop falls back to the plain instructions
+        let program = parse_program("tgl 3\ninc a\ndec b\njnz b -2").unwrap();
+        let mut machine = Machine::optimized(&program).with_register('b', 3).unwrap();
+        assert_eq!(Halt::Finished, machine.run(None).unwrap());
+        assert_eq!(1, machine.register('a').unwrap());
+        assert_eq!(2, machine.register('b').unwrap());
+    }
+
+    #[test]
+    fn test_overflow() {
+        let program = parse_program("cpy a b\ninc a").unwrap();
+        let mut machine = Machine::new(&program).with_register('a', i64::MAX).unwrap();
+        assert!(machine.run(None).is_err());
+        assert_eq!(i64::MAX, machine.register('b').unwrap());
+
+        let program = parse_program("inc b\ntgl a").unwrap();
+        let mut machine = Machine::new(&program).with_register('a', i64::MAX).unwrap();
+        machine.run(None).unwrap();
+        assert_eq!(1, machine.register('b').unwrap());
+        assert_eq!(program, machine.vm.program);
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let program =
+            parse_program("cpy 41 a\ninc a\ndec c\njnz c -2\njnz a 4\ntgl b\njnz 1 c\ndec a")
+                .unwrap();
+        let listing = disassemble(&Machine::new(&program).vm);
+        assert_eq!(
+            vec![
+                "        0  cpy 41 a",
+                "L1:     1  inc a",
+                "        2  dec c",
+                "        3  jnz c L1",
+                "        4  jnz a halt",
+                "        5  tgl b",
+                "        6  jnz 1 c",
+                "        7  dec a",
+            ],
+            listing.lines().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_limits_and_output() {
+        let program = parse_program("out a\ninc a\njnz 1 -2").unwrap();
+        let mut machine = Machine::new(&program);
+        assert_eq!(Halt::StepLimit, machine.run(Some(100)).unwrap());
+        assert_eq!(100, machine.vm.steps);
+        let mut machine = Machine::new(&program).with_register('a', 5).unwrap();
+        assert_eq!(
+            Halt::Stopped,
+            machine.run_until(|m| m.output.len() >= 3, None).unwrap()
+        );
+        assert_eq!(vec![5, 6, 7], machine.vm.output);
+    }
+}
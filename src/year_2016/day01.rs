@@ -1,77 +1,90 @@
 use crate::util::input::get_input_string;
-use std::collections::HashSet;
+use std::{env, fs};
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 
 use crate::data::Point2D;
 use crate::day::Day;
 use crate::util::cardinal::{Cardinal, Turn};
+use crate::util::polyline::Polyline;
 
-pub struct Day01;
+// Path to draw the route to, as ASCII
+const RENDER_VAR: &str = "AOC_RENDER";
 
-impl Cardinal {
-    fn forward(self, raw_magnitude: u16) -> (i32, i32) {
-        let magnitude: i32 = raw_magnitude.into();
-        match self {
-            Cardinal::North => (0, magnitude),
-            Cardinal::East => (magnitude, 0),
-            Cardinal::South => (0, -magnitude),
-            Cardinal::West => (-magnitude, 0),
+fn parse(input: &str) -> Result<Vec<(Turn, u16)>> {
+    let mut instructions: Vec<(Turn, u16)> = Vec::new();
+    for raw in input.split(',') {
+        let clean = raw.trim();
+        if clean.is_empty() {
+            continue;
         }
+        let mut chars = clean.chars();
+        let turn = match chars.next() {
+            Some(ch) => Turn::try_from(ch)?,
+            None => bail!("Expected a char"),
+        };
+        let magnitude: u16 = chars.collect::<String>().parse()?;
+        instructions.push((turn, magnitude));
     }
+    Ok(instructions)
 }
 
+fn walk(instructions: &[(Turn, u16)]) -> Polyline {
+    let mut route = Polyline::new(Point2D::new(0, 0));
+    let mut facing = Cardinal::North;
+    for (turn, magnitude) in instructions {
+        facing = facing.turn(*turn);
+        route.push(facing, i64::from(*magnitude));
+    }
+    route
+}
+
+fn taxicab(position: Point2D<i64>) -> i64 {
+    position.x.abs() + position.y.abs()
+}
+
+pub struct Day01;
+
 impl Day for Day01 {
     fn main() -> Result<()> {
-        let input = get_input_string(2016, 01)?;
-        let mut instructions: Vec<(Turn, u16)> = Vec::new();
-        for raw in input.split(',') {
-            let clean = raw.trim();
-            if clean.is_empty() {
-                continue;
-            }
-            let mut chars = clean.chars();
-            let turn = match chars.next() {
-                Some('R') => Turn::Right,
-                Some('L') => Turn::Left,
-                Some(x) => bail!("Unexpected char {x}"),
-                None => bail!("Expected a char"),
-            };
-            let magnitude: u16 = chars.collect::<String>().parse()?;
-            instructions.push((turn, magnitude));
-        }
-        // Find final position
-        let mut position: Point2D<i32> = Point2D::new(0, 0);
-        let mut facing = Cardinal::North;
-        for (turn, magnitude) in &instructions {
-            facing = facing.turn(*turn);
-            position += facing.forward(*magnitude);
-        }
+        let route = walk(&parse(&get_input_string(2016, 01)?)?);
+        let position = route.end();
         println!(
             "Final position is {position:?}, taxicab distance from (0, 0) is {}",
-            position.x.abs() + position.y.abs()
+            taxicab(position)
         );
-
-        // Find first duplicated position
-        let mut position: Point2D<i32> = Point2D::new(0, 0);
-        let mut facing = Cardinal::North;
-        let mut positions: HashSet<Point2D<i32>> = HashSet::new();
-        positions.insert(position);
-        'duplicate_find: for (turn, magnitude) in &instructions {
-            facing = facing.turn(*turn);
-            for _ in 0..*magnitude {
-                position += facing.forward(1);
-                if positions.contains(&position) {
-                    println!(
-                        "Duplicate position is {position:?}, taxicab distance from (0, 0) is {}",
-                        position.x.abs() + position.y.abs()
-                    );
-                    break 'duplicate_find;
-                } else {
-                    positions.insert(position);
-                }
-            }
+        match route.first_revisit() {
+            Some(revisit) => println!(
+                "Duplicate position is {:?} after {} blocks, taxicab distance from (0, 0) is {}",
+                revisit.point,
+                revisit.distance,
+                taxicab(revisit.point)
+            ),
+            None => println!("No position is visited twice"),
+        }
+        println!("Positions visited again: {}", route.revisits().len());
+        if let Ok(path) = env::var(RENDER_VAR) {
+            fs::write(&path, route.render() + "\n")
+                .with_context(|| format!("Couldn't write '{path}'"))?;
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example_day_2016_01() {
+        for (input, distance) in [("R2, L3", 5), ("R2, R2, R2", 2), ("R5, L5, R5, R3", 12)] {
+            assert_eq!(distance, taxicab(walk(&parse(input).unwrap()).end()));
+        }
+        let route = walk(&parse("R8, R4, R4, R8").unwrap());
+        let revisit = route.first_revisit().unwrap();
+        assert_eq!(Point2D::new(4, 0), revisit.point);
+        assert_eq!(4, taxicab(revisit.point));
+        assert_eq!(20, revisit.distance);
+        assert!(parse("X3").is_err());
+    }
+}
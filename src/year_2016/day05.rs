@@ -1,41 +1,63 @@
 use crate::util::input::get_input_string;
 
 use anyhow::{Context, Result};
-use lazy_regex::{regex_captures, regex_is_match};
-use md5;
 
 use crate::day::Day;
+use crate::util::hashmine::{leading_zeros, mine, nibble, Salted};
+
+fn hex_char(n: u8) -> Result<char> {
+    char::from_digit(n.into(), 16).with_context(|| format!("{n} is not a hex digit"))
+}
+
+// The password from the sixth hex digit of each interesting hash, and the passcode
+// placing each seventh digit at the position given by the sixth
+fn crack(door: &str) -> Result<(String, String)> {
+    let salted = Salted::new(door);
+    let mut password: Vec<char> = Vec::new();
+    let mut passcode: [Option<char>; 8] = [None; 8];
+    let hashes = mine(0, |i| {
+        let digest = salted.digest(i);
+        (leading_zeros(&digest) >= 5).then(|| (nibble(&digest, 5), nibble(&digest, 6)))
+    });
+    for (_, (a, b)) in hashes {
+        if password.len() < 8 {
+            password.push(hex_char(a)?);
+        }
+        if let Some(slot) = passcode.get_mut(a as usize) {
+            if slot.is_none() {
+                *slot = Some(hex_char(b)?);
+            }
+        }
+        if password.len() == 8 && passcode.iter().all(|x| x.is_some()) {
+            break;
+        }
+    }
+    let password: String = password.iter().collect();
+    let passcode: String = passcode.iter().flatten().collect();
+    Ok((password, passcode))
+}
 
 pub struct Day05;
 
 impl Day for Day05 {
     fn main() -> Result<()> {
-        let input = get_input_string(2016, 05)?;
-        let input = input.trim();
-        let mut password: Vec<char> = Vec::new();
-        let mut passcode: [Option<char>; 8] = [None; 8];
-        for i in 0..usize::MAX {
-            let hash = format!("{:x}", md5::compute(format!("{input}{i}")));
-            if let Some((_, a, b)) = regex_captures!("^00000(.)(.)", &hash) {
-                if password.len() < 8 {
-                    password.push(a.chars().nth(0).with_context(|| "No character!")?);
-                }
-                if regex_is_match!("^[0-7]$", a) {
-                    let pos: usize = a.parse()?;
-                    if passcode[pos].is_none() {
-                        passcode[pos] = Some(b.chars().nth(0).with_context(|| "No character!")?);
-                    }
-                }
-                if password.len() == 8 && passcode.iter().all(|x| x.is_some()) {
-                    break;
-                }
-                println!("{}", password.len());
-            }
-        }
-        let password: String = password.iter().collect();
+        let (password, passcode) = crack(&get_input_string(2016, 05)?)?;
         println!("Password is {password}");
-        let passcode: String = passcode.iter().map(|x| x.unwrap()).collect();
         println!("Passcode is {passcode}");
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore]
+    fn bench_example() {
+        assert_eq!(
+            ("18f47a30".to_string(), "05ace8e3".to_string()),
+            crack("abc").unwrap()
+        );
+    }
+}
@@ -1,144 +1,149 @@
-use crate::util::input::get_input;
-use std::io::{BufRead, BufReader};
+use crate::util::input::get_input_string;
+use std::collections::VecDeque;
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use lazy_regex::regex_captures;
 
 use crate::day::Day;
 use crate::util::expand::expand;
 
-#[derive(Copy, Clone)]
-enum ValueSource {
-    Literal(u32),
-    BotHigh(usize),
-    BotLow(usize),
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Target {
+    Bot(usize),
+    Output(usize),
 }
 
-#[derive(Copy, Clone)]
-enum ValueTarget {
-    Output,
-    Bot,
+impl Target {
+    fn from_args(kind: &str, num: &str) -> Result<Self> {
+        let num: usize = num.parse()?;
+        match kind {
+            "bot" => Ok(Target::Bot(num)),
+            "output" => Ok(Target::Output(num)),
+            _ => bail!("Unexpected target '{kind}'"),
+        }
+    }
 }
 
-#[derive(Copy, Clone, Default)]
-struct Bot {
-    input_a: Option<ValueSource>,
-    input_b: Option<ValueSource>,
-    output_high: Option<ValueTarget>,
-    output_low: Option<ValueTarget>,
+// Where each bot sends its (low, high) chips, and the chips handed out at the start
+#[derive(Clone, Debug, Default)]
+struct Network {
+    rules: Vec<Option<(Target, Target)>>,
+    inputs: Vec<(u32, usize)>,
 }
 
-impl Bot {
-    fn add_source(&mut self, source: ValueSource) -> Result<()> {
-        match (self.input_a, self.input_b) {
-            (None, _) => {
-                self.input_a = Some(source);
-            }
-            (_, None) => {
-                self.input_b = Some(source);
-            }
-            _ => bail!("Too many sources!"),
-        }
-        Ok(())
-    }
-
-    fn add_output(&mut self, low: ValueTarget, high: ValueTarget) -> Result<()> {
-        match (self.output_low, self.output_high) {
-            (None, None) => {
-                self.output_low = Some(low);
-                self.output_high = Some(high);
+impl Network {
+    fn parse(input: &str) -> Result<Self> {
+        let mut network = Network::default();
+        for line in input.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+            if let Some((_, value, bot)) =
+                regex_captures!("^value ([0-9]+) goes to bot ([0-9]+)$", line)
+            {
+                network.inputs.push((value.parse()?, bot.parse()?));
+            } else if let Some((_, bot, low_kind, low_num, high_kind, high_num)) = regex_captures!(
+                "^bot ([0-9]+) gives low to (output|bot) ([0-9]+) and high to (output|bot) ([0-9]+)$",
+                line
+            ) {
+                let bot: usize = bot.parse()?;
+                let low = Target::from_args(low_kind, low_num)?;
+                let high = Target::from_args(high_kind, high_num)?;
+                expand(&mut network.rules, bot);
+                if network.rules[bot].replace((low, high)).is_some() {
+                    bail!("Bot {bot} has more than one rule");
+                }
+            } else {
+                bail!("Unrecognized line '{line}'");
             }
-            _ => bail!("Too many outputs!"),
         }
-        Ok(())
+        Ok(network)
     }
+}
 
-    fn get_values(&mut self, bots: &mut [Bot]) -> Result<(u32, u32)> {
-        let a = match self.input_a {
-            Some(ValueSource::Literal(x)) => x,
-            Some(ValueSource::BotHigh(i)) => {
-                let mut bot = bots[i];
-                let res = bot.get_high(bots)?;
-                bots[i] = bot;
-                res
-            }
-            Some(ValueSource::BotLow(i)) => {
-                let mut bot = bots[i];
-                let res = bot.get_low(bots)?;
-                bots[i] = bot;
-                res
-            }
-            _ => bail!("No input_a available"),
-        };
-        self.input_a = Some(ValueSource::Literal(a));
-        let b = match self.input_b {
-            Some(ValueSource::Literal(x)) => x,
-            Some(ValueSource::BotHigh(i)) => {
-                let mut bot = bots[i];
-                let res = bot.get_high(bots)?;
-                bots[i] = bot;
-                res
-            }
-            Some(ValueSource::BotLow(i)) => {
-                let mut bot = bots[i];
-                let res = bot.get_low(bots)?;
-                bots[i] = bot;
-                res
-            }
-            _ => bail!("No input_b available"),
-        };
-        self.input_b = Some(ValueSource::Literal(b));
-        if a < b {
-            Ok((a, b))
-        } else {
-            Ok((b, a))
-        }
-    }
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct Comparison {
+    bot: usize,
+    low: u32,
+    high: u32,
+}
 
-    fn get_low(&mut self, bots: &mut [Bot]) -> Result<u32> {
-        self.get_values(bots).map(|(low, _)| low)
+// Bots act as soon as they hold two chips, so they're queued when the second arrives
+fn give(
+    hands: &mut Vec<Vec<u32>>,
+    full: &mut VecDeque<usize>,
+    bot: usize,
+    value: u32,
+) -> Result<()> {
+    expand(hands, bot);
+    if hands[bot].len() == 2 {
+        bail!("Bot {bot} was given a third chip ({value})");
     }
-
-    fn get_high(&mut self, bots: &mut [Bot]) -> Result<u32> {
-        self.get_values(bots).map(|(_, high)| high)
+    hands[bot].push(value);
+    if hands[bot].len() == 2 {
+        full.push_back(bot);
     }
+    Ok(())
 }
 
-#[derive(Copy, Clone, Default)]
-struct Output {
-    source: Option<ValueSource>,
+#[derive(Clone, Debug, Default)]
+struct Simulation {
+    // In the order the bots handed off their chips
+    comparisons: Vec<Comparison>,
+    outputs: Vec<Vec<u32>>,
 }
 
-impl Output {
-    fn add_source(&mut self, source: ValueSource) -> Result<()> {
-        match self.source {
-            None => {
-                self.source = Some(source);
+impl Simulation {
+    fn run(network: &Network) -> Result<Self> {
+        let mut simulation = Simulation::default();
+        let mut hands: Vec<Vec<u32>> = Vec::new();
+        let mut full: VecDeque<usize> = VecDeque::new();
+        for (value, bot) in &network.inputs {
+            give(&mut hands, &mut full, *bot, *value)?;
+        }
+        while let Some(bot) = full.pop_front() {
+            let (low_target, high_target) = network
+                .rules
+                .get(bot)
+                .copied()
+                .flatten()
+                .with_context(|| format!("Bot {bot} holds two chips but has no rule"))?;
+            let (a, b) = (hands[bot][0], hands[bot][1]);
+            hands[bot].clear();
+            let comparison = Comparison {
+                bot,
+                low: a.min(b),
+                high: a.max(b),
+            };
+            simulation.comparisons.push(comparison);
+            for (target, value) in [(low_target, comparison.low), (high_target, comparison.high)] {
+                match target {
+                    Target::Bot(other) => give(&mut hands, &mut full, other, value)?,
+                    Target::Output(bin) => {
+                        expand(&mut simulation.outputs, bin);
+                        simulation.outputs[bin].push(value);
+                    }
+                }
             }
-            _ => bail!("Too many sources!"),
         }
-        Ok(())
+        Ok(simulation)
     }
 
-    fn get_value(&mut self, bots: &mut [Bot]) -> Result<u32> {
-        let value = match self.source {
-            Some(ValueSource::Literal(x)) => x,
-            Some(ValueSource::BotHigh(i)) => {
-                let mut bot = bots[i];
-                let res = bot.get_high(bots)?;
-                bots[i] = bot;
-                res
-            }
-            Some(ValueSource::BotLow(i)) => {
-                let mut bot = bots[i];
-                let res = bot.get_low(bots)?;
-                bots[i] = bot;
-                res
-            }
-            _ => bail!("No source found for Output"),
-        };
-        self.source = Some(ValueSource::Literal(value));
-        Ok(value)
+    // The bot that compared the two values, in either order
+    fn comparer(&self, a: u32, b: u32) -> Option<usize> {
+        self.comparisons
+            .iter()
+            .find(|c| (c.low, c.high) == (a.min(b), a.max(b)))
+            .map(|c| c.bot)
+    }
+
+    fn output(&self, bin: usize) -> &[u32] {
+        self.outputs.get(bin).map_or(&[], |chips| chips.as_slice())
+    }
+
+    // The chip in a bin that should have received exactly one
+    fn output_chip(&self, bin: usize) -> Result<u32> {
+        match self.output(bin) {
+            [chip] => Ok(*chip),
+            chips => bail!("Output {bin} holds {} chips, expected one", chips.len()),
+        }
     }
 }
 
@@ -146,68 +151,77 @@ pub struct Day10;
 
 impl Day for Day10 {
     fn main() -> Result<()> {
-        let input = BufReader::new(get_input(2016, 10)?);
-        let mut bots: Vec<Bot> = Vec::new();
-        let mut outputs: Vec<Output> = Vec::new();
-        for line in input.lines().map(|l| l.unwrap()) {
-            if let Some((_, value, bot_num)) =
-                regex_captures!("value ([0-9]+) goes to bot ([0-9]+)", &line)
-            {
-                let value: u32 = value.parse()?;
-                let bot_num: usize = bot_num.parse()?;
-                expand(&mut bots, bot_num);
-                bots[bot_num].add_source(ValueSource::Literal(value))?;
-            } else if let Some((_, bot_num, low_target, low_num, high_target, high_num)) = regex_captures!(
-                "bot ([0-9]+) gives low to (output|bot) ([0-9]+) and high to (output|bot) ([0-9]+)",
-                &line
-            ) {
-                let bot_num: usize = bot_num.parse()?;
-                let low_num: usize = low_num.parse()?;
-                let high_num: usize = high_num.parse()?;
-                let low = match low_target {
-                    "output" => {
-                        expand(&mut outputs, low_num);
-                        outputs[low_num].add_source(ValueSource::BotLow(bot_num))?;
-                        ValueTarget::Output
-                    }
-                    "bot" => {
-                        expand(&mut bots, low_num);
-                        bots[low_num].add_source(ValueSource::BotLow(bot_num))?;
-                        ValueTarget::Bot
-                    }
-                    _ => bail!("Unexpected target '{low_target}'"),
-                };
-                let high = match high_target {
-                    "output" => {
-                        expand(&mut outputs, high_num);
-                        outputs[high_num].add_source(ValueSource::BotHigh(bot_num))?;
-                        ValueTarget::Output
-                    }
-                    "bot" => {
-                        expand(&mut bots, high_num);
-                        bots[high_num].add_source(ValueSource::BotHigh(bot_num))?;
-                        ValueTarget::Bot
-                    }
-                    _ => bail!("Unexpected target '{high_target}'"),
-                };
-                expand(&mut bots, bot_num);
-                bots[bot_num].add_output(low, high)?;
-            }
-        }
-        for i in 0..bots.len() {
-            let mut bot = bots[i];
-            let (low, high) = bot.get_values(&mut bots)?;
-            bots[i] = bot;
-            if low == 17 && high == 61 {
-                println!("Bot {i} compares 17 to 61");
-                break;
-            }
-        }
+        let network = Network::parse(&get_input_string(2016, 10)?)?;
+        let simulation = Simulation::run(&network)?;
+        let bot = simulation
+            .comparer(61, 17)
+            .with_context(|| "No bot compares 17 to 61")?;
+        println!("Bot {bot} compares 17 to 61");
         let mut product = 1;
-        for i in 0..=2 {
-            product *= outputs[i].get_value(&mut bots)?;
+        for bin in 0..=2 {
+            product *= simulation.output_chip(bin)?;
         }
         println!("Product of first 3 chip outputs is {product}");
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "value 5 goes to bot 2
+        bot 2 gives low to bot 1 and high to bot 0
+        value 3 goes to bot 1
+        bot 1 gives low to output 1 and high to bot 0
+        bot 0 gives low to output 2 and high to output 0
+        value 2 goes to bot 2";
+
+    #[test]
+    fn example_day_2016_10() {
+        let simulation = Simulation::run(&Network::parse(EXAMPLE).unwrap()).unwrap();
+        assert_eq!(Some(2), simulation.comparer(5, 2));
+        assert_eq!(Some(2), simulation.comparer(2, 5));
+        assert_eq!(None, simulation.comparer(2, 4));
+        assert_eq!(
+            vec![
+                Comparison {
+                    bot: 2,
+                    low: 2,
+                    high: 5
+                },
+                Comparison {
+                    bot: 1,
+                    low: 2,
+                    high: 3
+                },
+                Comparison {
+                    bot: 0,
+                    low: 3,
+                    high: 5
+                },
+            ],
+            simulation.comparisons
+        );
+        assert_eq!(&[5], simulation.output(0));
+        assert_eq!(2, simulation.output_chip(1).unwrap());
+        assert_eq!(3, simulation.output_chip(2).unwrap());
+        assert!(simulation.output(3).is_empty());
+        assert!(simulation.output_chip(3).is_err());
+    }
+
+    #[test]
+    fn test_malformed_networks() {
+        let third_chip = "value 1 goes to bot 0\nvalue 2 goes to bot 0\nvalue 3 goes to bot 0";
+        let error = Simulation::run(&Network::parse(third_chip).unwrap()).unwrap_err();
+        assert_eq!("Bot 0 was given a third chip (3)", error.to_string());
+        let no_rule = "value 1 goes to bot 0\nvalue 2 goes to bot 0\n\
+            bot 0 gives low to bot 1 and high to output 0\nvalue 3 goes to bot 1";
+        let error = Simulation::run(&Network::parse(no_rule).unwrap()).unwrap_err();
+        assert_eq!("Bot 1 holds two chips but has no rule", error.to_string());
+        let two_rules = "bot 0 gives low to bot 1 and high to output 0\n\
+            bot 0 gives low to bot 2 and high to output 1";
+        assert!(Network::parse(two_rules).is_err());
+        assert!(Network::parse("value 1 goes to robot 0").is_err());
+    }
+}
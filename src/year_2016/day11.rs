@@ -1,16 +1,18 @@
-use crate::util::input::get_input;
-use std::cmp::Ordering;
-use std::collections::HashSet;
-use std::io::{BufRead, BufReader};
+use crate::util::input::get_input_string;
+use std::env;
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
+use itertools::Itertools;
 use lazy_regex::{regex, regex_captures};
 
 use crate::data::StringIdMap;
 use crate::day::Day;
-use crate::util::expand::expand;
-use crate::util::ordered_vec::OrderedVec;
-use crate::util::priority_queue::PriorityQueue;
+use crate::util::search::{solve, Puzzle, Reversible, Strategy};
+
+// Search strategy to use, 'bfs', 'astar' (the default) or 'bidirectional'
+const SEARCH_VAR: &str = "AOC_SEARCH";
+
+const FLOOR_NAMES: [&str; 4] = ["first", "second", "third", "fourth"];
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 enum Device {
@@ -18,349 +20,251 @@ enum Device {
     Microchip(usize),
 }
 
-impl Ord for Device {
-    fn cmp(&self, other: &Self) -> Ordering {
-        match (*self, *other) {
-            (Device::Generator(x), Device::Generator(y)) => x.cmp(&y),
-            (Device::Microchip(x), Device::Microchip(y)) => x.cmp(&y),
-            (Device::Generator(x), Device::Microchip(y)) => match x.cmp(&y) {
-                Ordering::Equal => Ordering::Greater,
-                z => z,
-            },
-            (Device::Microchip(x), Device::Generator(y)) => match x.cmp(&y) {
-                Ordering::Equal => Ordering::Less,
-                z => z,
-            },
-        }
-    }
-}
-
-impl PartialOrd for Device {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
 impl Device {
-    fn is_pair(&self, other: &Self) -> bool {
-        match (*self, *other) {
-            (Device::Generator(x), Device::Microchip(y)) => x == y,
-            (Device::Microchip(x), Device::Generator(y)) => x == y,
-            _ => false,
-        }
-    }
-
-    fn normalize(&self) -> NormalizedDevice {
-        match self {
-            Device::Generator(_) => NormalizedDevice::Generator,
-            Device::Microchip(_) => NormalizedDevice::Microchip,
-        }
+    fn describe(&self, names: &StringIdMap) -> String {
+        let (id, kind) = match self {
+            Device::Generator(id) => (id, "generator"),
+            Device::Microchip(id) => (id, "microchip"),
+        };
+        format!("{} {kind}", names.name(*id).map_or("?", |n| n.as_str()))
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Ord, PartialOrd)]
-enum NormalizedDevice {
-    Generator,
-    Microchip,
-    Pair,
+// Floor of each element's generator and microchip
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+struct Pair {
+    generator: usize,
+    microchip: usize,
 }
 
 #[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
 struct State {
-    floors: Vec<OrderedVec<Device>>,
-    elevator_floor: usize,
-}
-
-#[derive(Eq, Hash, PartialEq)]
-struct NormalizedState {
-    floors: Vec<OrderedVec<NormalizedDevice>>,
-    elevator_floor: usize,
+    elevator: usize,
+    // Indexed by element id
+    pairs: Vec<Pair>,
 }
 
 impl State {
-    fn complete(&self) -> bool {
-        let last_floor = self.floors.len() - 1;
-        for i in 0..last_floor {
-            if !self.floors[i].is_empty() {
-                return false;
-            }
+    fn floor(&self, device: Device) -> usize {
+        match device {
+            Device::Generator(id) => self.pairs[id].generator,
+            Device::Microchip(id) => self.pairs[id].microchip,
         }
-        self.elevator_floor == last_floor
     }
 
-    fn valid(&self) -> bool {
-        for i in 0..self.floors.len() {
-            match self.floors[i].len() {
-                0 => continue,
-                1 => continue,
-                len => {
-                    let mut has_generator = false;
-                    let mut has_unpaired_microchip = false;
-                    for j in 0..len {
-                        match self.floors[i][j] {
-                            Device::Generator(_) => {
-                                has_generator = true;
-                            }
-                            Device::Microchip(_) => {
-                                has_unpaired_microchip = if has_unpaired_microchip || j >= len - 1 {
-                                    true
-                                } else {
-                                    !self.floors[i][j].is_pair(&self.floors[i][j + 1])
-                                };
-                            }
-                        }
-                    }
-                    if has_generator && has_unpaired_microchip {
-                        return false;
-                    }
-                }
-            }
-        }
-        true
+    fn devices(&self) -> impl Iterator<Item = Device> + '_ {
+        (0..self.pairs.len()).flat_map(|id| [Device::Generator(id), Device::Microchip(id)])
     }
+}
 
-    fn move_each(&self) -> Vec<Self> {
-        let mut new_states = Vec::new();
-        let floor_len = self.floors[self.elevator_floor].len();
-
-        if self.elevator_floor < self.floors.len() - 1 {
-            let upper = self.elevator_floor + 1;
-
-            // Try Moving Pairs Up
-            let mut pairs_moved = 0;
-            for i in 0..floor_len - 1 {
-                for j in i + 1..floor_len {
-                    let mut new_state = self.clone();
-                    let b = new_state.floors[new_state.elevator_floor].remove(j);
-                    let a = new_state.floors[new_state.elevator_floor].remove(i);
-                    new_state.floors[upper].push(a);
-                    new_state.floors[upper].push(b);
-                    new_state.elevator_floor = upper;
-                    if new_state.valid() {
-                        new_states.push(new_state);
-                        pairs_moved += 1;
-                    }
-                }
-            }
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Move {
+    devices: Vec<Device>,
+    from: usize,
+    to: usize,
+}
 
-            if pairs_moved == 0 {
-                // Try Moving Singletons Up
-                for i in 0..floor_len {
-                    let mut new_state = self.clone();
-                    let moved = new_state.floors[new_state.elevator_floor].remove(i);
-                    new_state.floors[upper].push(moved);
-                    new_state.elevator_floor = upper;
-                    if new_state.valid() {
-                        new_states.push(new_state);
-                    }
-                }
-            }
-        }
+#[derive(Clone, Debug, Default)]
+struct Facility {
+    names: StringIdMap,
+    floors: usize,
+}
 
-        if self.elevator_floor > 0 {
-            let lower = self.elevator_floor - 1;
-            let mut is_empty = true;
-            for i in 0..=lower {
-                if !self.floors[i].is_empty() {
-                    is_empty = false;
-                    break;
-                }
-            }
-            if !is_empty {
-                // Try Moving Singletons Down
-                let mut singletons_moved = 0;
-                for i in 0..floor_len {
-                    let mut new_state = self.clone();
-                    let moved = new_state.floors[new_state.elevator_floor].remove(i);
-                    new_state.floors[lower].push(moved);
-                    new_state.elevator_floor = lower;
-                    if new_state.valid() {
-                        new_states.push(new_state);
-                        singletons_moved += 1;
+impl Facility {
+    fn parse(input: &str) -> Result<(Self, State)> {
+        let mut facility = Facility::default();
+        let mut generators: Vec<Option<usize>> = Vec::new();
+        let mut microchips: Vec<Option<usize>> = Vec::new();
+        for line in input.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+            let (_, floor) = regex_captures!("^The ([a-z]+) floor contains", line)
+                .with_context(|| format!("Unrecognized line '{line}'"))?;
+            let floor = FLOOR_NAMES
+                .iter()
+                .position(|name| *name == floor)
+                .with_context(|| format!("Unexpected floor '{floor}'"))?;
+            facility.floors = facility.floors.max(floor + 1);
+            for (found, pattern) in [
+                (&mut generators, regex!("([a-z]+) generator")),
+                (&mut microchips, regex!("([a-z]+)-compatible microchip")),
+            ] {
+                for caps in pattern.captures_iter(line) {
+                    let id = facility.names.to_id(&caps[1]);
+                    if found.len() <= id {
+                        found.resize(id + 1, None);
                     }
-                }
-
-                if singletons_moved == 0 {
-                    // Try Moving Pairs Down
-                    for i in 0..floor_len - 1 {
-                        for j in i..floor_len {
-                            let mut new_state = self.clone();
-                            let b = new_state.floors[new_state.elevator_floor].remove(j);
-                            let a = new_state.floors[new_state.elevator_floor].remove(i);
-                            new_state.floors[lower].push(a);
-                            new_state.floors[lower].push(b);
-                            new_state.elevator_floor = lower;
-                            if new_state.valid() {
-                                new_states.push(new_state);
-                            }
-                        }
+                    if found[id].replace(floor).is_some() {
+                        bail!("Duplicate {}", &caps[0]);
                     }
                 }
             }
         }
-
-        new_states
+        let mut state = State::default();
+        for (id, name) in facility.names.iter() {
+            let generator = generators.get(id).copied().flatten();
+            let microchip = microchips.get(id).copied().flatten();
+            let (Some(generator), Some(microchip)) = (generator, microchip) else {
+                bail!("The {name} generator and microchip must both be somewhere");
+            };
+            state.pairs.push(Pair {
+                generator,
+                microchip,
+            });
+        }
+        Ok((facility, state))
     }
 
-    fn closeness(&self) -> usize {
-        let mut empty_floor_count = 0;
-        for i in 0..self.floors.len() {
-            if self.floors[i].is_empty() {
-                empty_floor_count += 1;
-            } else {
-                break;
+    // Adds a generator and microchip for each element to the first floor
+    fn add_pairs(&mut self, state: &mut State, names: &[&str]) -> Result<()> {
+        for name in names {
+            if self.names.get(*name).is_some() {
+                bail!("Input already contains {name}");
             }
+            self.names.to_id(*name);
+            state.pairs.push(Pair {
+                generator: 0,
+                microchip: 0,
+            });
         }
-        let floor_score: usize = self
-            .floors
-            .iter()
-            .enumerate()
-            .map(|(i, f)| (i + 1) * 2 * f.len())
-            .sum();
-        empty_floor_count * 10000 + floor_score
+        Ok(())
     }
 
-    fn normalize(&self) -> NormalizedState {
-        let floors: Vec<OrderedVec<NormalizedDevice>> = self
-            .floors
-            .iter()
-            .map(|f| normalize_devices(f.as_slice()))
-            .collect();
-        NormalizedState {
-            floors,
-            elevator_floor: self.elevator_floor,
-        }
+    fn describe(&self, step: &Move) -> String {
+        format!(
+            "Floor {} to {}: {}",
+            step.from + 1,
+            step.to + 1,
+            step.devices
+                .iter()
+                .map(|d| d.describe(&self.names))
+                .join(", ")
+        )
     }
 }
 
-fn normalize_devices(devices: &[Device]) -> OrderedVec<NormalizedDevice> {
-    match devices.len() {
-        0 => OrderedVec::new(),
-        1 => OrderedVec::from_one(devices[0].normalize()),
-        len => {
-            // 2+
-            let mut res = OrderedVec::new();
-            let mut i = 0;
-            while i < len - 1 {
-                if devices[i].is_pair(&devices[i + 1]) {
-                    i += 2;
-                    res.push(NormalizedDevice::Pair);
-                } else {
-                    i += 1;
-                    res.push(devices[i].normalize());
-                }
+impl Puzzle for Facility {
+    type State = State;
+    type Move = Move;
+    type Key = (usize, Vec<Pair>);
+
+    // The elevator carries one or two devices up or down a floor
+    fn moves(&self, state: &State) -> Vec<(Move, State)> {
+        let here: Vec<Device> = state
+            .devices()
+            .filter(|d| state.floor(*d) == state.elevator)
+            .collect();
+        let mut moves = Vec::new();
+        for to in [state.elevator + 1, state.elevator.wrapping_sub(1)] {
+            if to >= self.floors {
+                continue;
             }
-            if i < devices.len() {
-                res.push(devices[i].normalize());
+            for devices in (1..=2).flat_map(|count| here.iter().copied().combinations(count)) {
+                let mut next = state.clone();
+                next.elevator = to;
+                for device in &devices {
+                    match *device {
+                        Device::Generator(id) => next.pairs[id].generator = to,
+                        Device::Microchip(id) => next.pairs[id].microchip = to,
+                    }
+                }
+                let step = Move {
+                    devices,
+                    from: state.elevator,
+                    to,
+                };
+                moves.push((step, next));
             }
-            res
         }
+        moves
     }
-}
 
-#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
-struct StatePriority {
-    closeness: usize,
-    steps: usize,
-}
+    // A microchip can't share a floor with another element's generator unless its own
+    // generator is there to shield it
+    fn valid(&self, state: &State) -> bool {
+        state.pairs.iter().all(|pair| {
+            pair.generator == pair.microchip
+                || state.pairs.iter().all(|p| p.generator != pair.microchip)
+        })
+    }
 
-impl Ord for StatePriority {
-    fn cmp(&self, other: &Self) -> Ordering {
-        if self.steps == other.steps {
-            if self.closeness == other.closeness {
-                Ordering::Equal
-            } else if self.closeness < other.closeness {
-                Ordering::Less
-            } else {
-                Ordering::Greater
-            }
-        } else if self.steps < other.steps {
-            Ordering::Greater
-        } else {
-            Ordering::Less
-        }
+    fn is_goal(&self, state: &State) -> bool {
+        let top = self.floors - 1;
+        state.elevator == top
+            && state
+                .pairs
+                .iter()
+                .all(|p| p.generator == top && p.microchip == top)
+    }
+
+    // Elements are interchangeable, so only the floors of each pair matter
+    fn key(&self, state: &State) -> (usize, Vec<Pair>) {
+        let mut pairs = state.pairs.clone();
+        pairs.sort_unstable();
+        (state.elevator, pairs)
     }
-}
 
-impl PartialOrd for StatePriority {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+    // Every trip up between two floors takes at most two devices, and every trip but the
+    // last needs one to come back down with the elevator
+    fn heuristic(&self, state: &State) -> usize {
+        (0..self.floors - 1)
+            .map(|floor| {
+                let below = state.devices().filter(|d| state.floor(*d) <= floor).count();
+                match below {
+                    0 => 0,
+                    // The elevator has to come down first, bringing something with it
+                    _ if state.elevator > floor => 2 * below,
+                    _ => (2 * below).saturating_sub(3).max(1),
+                }
+            })
+            .sum()
     }
 }
 
-fn read_input() -> Result<(State, StringIdMap)> {
-    let reg_microchip = regex!("([a-z]+)-compatible microchip");
-    let reg_generator = regex!("([a-z]+) generator");
-    let input = BufReader::new(get_input(2016, 11)?);
-    let mut keywords = StringIdMap::default();
-    let mut state = State::default();
-    for line in input.lines().map(|l| l.unwrap()) {
-        if let Some((_, floor)) = regex_captures!("(first|second|third|fourth) floor", &line) {
-            let floor = match floor {
-                "first" => 0,
-                "second" => 1,
-                "third" => 2,
-                "fourth" => 3,
-                _ => bail!("Unexpected floor '{floor}'"),
-            };
-            expand(&mut state.floors, floor);
-            for caps in reg_microchip.captures_iter(&line) {
-                let id = keywords.to_id(&caps[1]);
-                state.floors[floor].push(Device::Microchip(id));
-            }
-            for caps in reg_generator.captures_iter(&line) {
-                let id = keywords.to_id(&caps[1]);
-                state.floors[floor].push(Device::Generator(id));
-            }
+impl Reversible for Facility {
+    fn goal(&self, start: &State) -> State {
+        let top = self.floors - 1;
+        State {
+            elevator: top,
+            pairs: vec![
+                Pair {
+                    generator: top,
+                    microchip: top
+                };
+                start.pairs.len()
+            ],
         }
     }
-    Ok((state, keywords))
 }
 
-fn minimum_steps(initial_state: &State) -> usize {
-    let mut seen: HashSet<NormalizedState> = HashSet::new();
-    let mut states = PriorityQueue::new();
-    states.push(initial_state.clone(), StatePriority::default());
-    while let Some((state, priority)) = states.pop() {
-        let normalized = state.normalize();
-        if seen.contains(&normalized) {
-            // Don't reprocess the same state again
-            continue;
-        } else {
-            seen.insert(normalized);
-        }
-        if state.complete() {
-            return priority.steps;
-        }
-        for new_state in state.move_each() {
-            let p = StatePriority {
-                steps: priority.steps + 1,
-                closeness: new_state.closeness(),
-            };
-            states.push(new_state, p);
-        }
-        if seen.len() % 10000 == 0 {
-            println!("Processing... {} / {}", states.len(), seen.len());
-        }
+fn report(facility: &Facility, start: State, strategy: Strategy) -> Result<()> {
+    if facility.floors == 0 || !facility.valid(&start) {
+        bail!("The devices start out unsafely");
+    }
+    let solution =
+        solve(facility, start, strategy).with_context(|| "Can't collect all objects safely")?;
+    println!(
+        "It took {} steps to collect all {} objects safely ({} states explored)",
+        solution.steps.len(),
+        facility.names.count() * 2,
+        solution.explored
+    );
+    for (step, _) in &solution.steps {
+        println!("  {}", facility.describe(step));
     }
-    return usize::MAX;
+    Ok(())
 }
 
 pub struct Day11;
 
 impl Day for Day11 {
     fn main() -> Result<()> {
-        let (mut initial_state, mut keywords) = read_input()?;
-        let steps = minimum_steps(&initial_state);
-        println!("It took {steps} steps to collect all objects safely");
-        let elerium = keywords.to_id("elerium");
-        let dilithium = keywords.to_id("dilithium");
-        initial_state.floors[0].push(Device::Generator(elerium));
-        initial_state.floors[0].push(Device::Microchip(elerium));
-        initial_state.floors[0].push(Device::Generator(dilithium));
-        initial_state.floors[0].push(Device::Microchip(dilithium));
-        let steps = minimum_steps(&initial_state);
-        println!("It took {steps} steps to collect additional objects safely");
+        let strategy = match env::var(SEARCH_VAR) {
+            Ok(name) => name.parse()?,
+            Err(_) => Strategy::default(),
+        };
+        let (mut facility, mut start) = Facility::parse(&get_input_string(2016, 11)?)?;
+        report(&facility, start.clone(), strategy)?;
+        facility.add_pairs(&mut start, &["elerium", "dilithium"])?;
+        report(&facility, start, strategy)?;
         Ok(())
     }
 }
@@ -368,140 +272,99 @@ impl Day for Day11 {
 #[cfg(test)]
 mod tests {
     use super::*;
-
-    #[test]
-    fn device_ordering() {
-        let data = vec![
-            (
-                Device::Generator(1),
-                Device::Microchip(1),
-                Ordering::Greater,
-            ),
-            (Device::Microchip(1), Device::Generator(1), Ordering::Less),
-            (Device::Generator(1), Device::Generator(2), Ordering::Less),
-            (
-                Device::Generator(2),
-                Device::Generator(1),
-                Ordering::Greater,
-            ),
-            (Device::Generator(1), Device::Generator(1), Ordering::Equal),
-            (Device::Microchip(1), Device::Microchip(2), Ordering::Less),
-            (
-                Device::Microchip(2),
-                Device::Microchip(1),
-                Ordering::Greater,
-            ),
-            (Device::Microchip(1), Device::Microchip(1), Ordering::Equal),
-        ];
-        for (left, right, expected) in data {
-            assert_eq!(
-                left.cmp(&right),
-                expected,
-                "Expected {left:?} {expected:?} {right:?}"
-            );
+    use crate::util::search::{a_star, bidirectional, breadth_first, Solution};
+
+    const EXAMPLE: &str = "
+        The first floor contains a hydrogen-compatible microchip and a lithium-compatible microchip.
+        The second floor contains a hydrogen generator.
+        The third floor contains a lithium generator.
+        The fourth floor contains nothing relevant.";
+
+    const PUZZLE: &str = "
+        The first floor contains a thulium generator, a thulium-compatible microchip, a plutonium generator, and a strontium generator.
+        The second floor contains a plutonium-compatible microchip and a strontium-compatible microchip.
+        The third floor contains a promethium generator, a promethium-compatible microchip, a ruthenium generator, and a ruthenium-compatible microchip.
+        The fourth floor contains nothing relevant.";
+
+    // Replays the moves, checking each is allowed and they finish the job
+    fn check(facility: &Facility, start: &State, solution: &Solution<Move, State>) {
+        let mut state = start.clone();
+        for (step, next) in &solution.steps {
+            let (_, expected) = facility
+                .moves(&state)
+                .into_iter()
+                .find(|(m, _)| m == step)
+                .unwrap();
+            assert_eq!(&expected, next);
+            assert!(facility.valid(next), "{}", facility.describe(step));
+            state = expected;
         }
+        assert!(facility.is_goal(&state));
     }
 
     #[test]
-    fn device_pairing() {
-        let data = vec![
-            (Device::Generator(1), Device::Microchip(1), true),
-            (Device::Generator(2), Device::Microchip(1), false),
-            (Device::Microchip(1), Device::Generator(1), true),
-            (Device::Microchip(2), Device::Generator(1), false),
-        ];
-        for (left, right, expected) in data {
-            assert_eq!(
-                left.is_pair(&right),
-                expected,
-                "Expected{} a pair {left:?}, {right:?}",
-                if !expected { " not" } else { "" }
-            );
+    fn example_day_2016_11() {
+        let (facility, start) = Facility::parse(EXAMPLE).unwrap();
+        assert_eq!(4, facility.floors);
+        for solution in [
+            breadth_first(&facility, start.clone()),
+            a_star(&facility, start.clone()),
+            bidirectional(&facility, start.clone()),
+        ] {
+            let solution = solution.unwrap();
+            assert_eq!(11, solution.steps.len());
+            check(&facility, &start, &solution);
         }
+        let solution = a_star(&facility, start.clone()).unwrap();
+        assert_eq!(
+            "Floor 1 to 2: hydrogen microchip",
+            facility.describe(&solution.steps[0].0)
+        );
     }
 
     #[test]
-    fn device_normalization() {
-        let data = vec![
-            (Device::Generator(1), NormalizedDevice::Generator),
-            (Device::Microchip(1), NormalizedDevice::Microchip),
-        ];
-
-        for (given, expected) in data {
-            assert_eq!(given.normalize(), expected);
-        }
-    }
-
-    #[test]
-    fn state_complete() {
-        let mut state = State::default();
-        expand(&mut state.floors, 3); // 4 Floors
-        state.elevator_floor = 3;
-        state.floors[3].push(Device::Generator(1));
-        state.floors[3].push(Device::Microchip(1));
-        assert!(state.complete(), "Should be marked complete {state:?}");
-    }
-
-    #[test]
-    fn state_incomplete() {
-        let mut state = State::default();
-        expand(&mut state.floors, 3); // 4 Floors
-        state.elevator_floor = 1;
-        state.floors[0].push(Device::Generator(1));
-        state.floors[1].push(Device::Microchip(1));
-        state.floors[2].push(Device::Microchip(2));
-        state.floors[2].push(Device::Generator(2));
-        assert!(!state.complete(), "Should be marked incomplete {state:?}");
+    fn test_puzzle_with_extra_pairs() {
+        let (mut facility, mut start) = Facility::parse(PUZZLE).unwrap();
+        let solution = a_star(&facility, start.clone()).unwrap();
+        assert_eq!(31, solution.steps.len());
+        check(&facility, &start, &solution);
+        let solution = bidirectional(&facility, start.clone()).unwrap();
+        assert_eq!(31, solution.steps.len());
+        check(&facility, &start, &solution);
+
+        facility
+            .add_pairs(&mut start, &["elerium", "dilithium"])
+            .unwrap();
+        assert!(facility.add_pairs(&mut start, &["elerium"]).is_err());
+        let solution = a_star(&facility, start.clone()).unwrap();
+        assert_eq!(55, solution.steps.len());
+        check(&facility, &start, &solution);
     }
 
     #[test]
-    fn state_valid() {
-        let mut state = State::default();
-        expand(&mut state.floors, 3); // 4 Floors
-        state.elevator_floor = 1;
-        state.floors[0].push(Device::Generator(1));
-        state.floors[1].push(Device::Microchip(1));
-        state.floors[2].push(Device::Microchip(2));
-        state.floors[2].push(Device::Generator(2));
-        assert!(state.valid(), "Should be marked valid {state:?}");
-    }
-
-    #[test]
-    fn state_invalid() {
-        let mut state = State::default();
-        expand(&mut state.floors, 3); // 4 Floors
-        state.elevator_floor = 1;
-        state.floors[1].push(Device::Generator(1));
-        state.floors[1].push(Device::Microchip(2));
-        state.floors[2].push(Device::Microchip(1));
-        state.floors[2].push(Device::Generator(2));
-        assert!(!state.valid(), "Should be marked invalid {state:?}");
-    }
-
-    #[test]
-    fn state_clone_deep() {
-        let mut state = State::default();
-        expand(&mut state.floors, 3); // 4 Floors
-        state.elevator_floor = 1;
-        state.floors[0].push(Device::Generator(1));
-        state.floors[1].push(Device::Microchip(1));
-        state.floors[2].push(Device::Microchip(2));
-        state.floors[2].push(Device::Generator(2));
-        let mut state2 = state.clone();
-        state2.floors[0].push(Device::Generator(3));
-        assert_ne!(state, state2, "Clone should be deep");
-    }
-
-    #[test]
-    fn priority_ordering_bug_fix() {
-        let a = StatePriority {
-            steps: 1,
-            closeness: 38,
-        };
-        let b = StatePriority {
-            steps: 2,
-            closeness: 42,
-        };
-        assert_eq!(std::cmp::Ordering::Greater, a.cmp(&b));
+    fn test_state_rules() {
+        let (facility, start) = Facility::parse(EXAMPLE).unwrap();
+        assert!(facility.valid(&start));
+        assert!(!facility.is_goal(&start));
+        // Hydrogen's microchip next to lithium's generator
+        let mut fried = start.clone();
+        fried.pairs[0].microchip = 2;
+        assert!(!facility.valid(&fried));
+        // Unless hydrogen's generator is there too
+        fried.pairs[0].generator = 2;
+        assert!(facility.valid(&fried));
+        let goal = facility.goal(&start);
+        assert!(facility.is_goal(&goal));
+        assert_eq!(0, facility.heuristic(&goal));
+        assert!(facility.heuristic(&start) <= 11);
+
+        // Swapping which element is which doesn't change the key
+        let mut swapped = start.clone();
+        swapped.pairs.swap(0, 1);
+        assert_ne!(start, swapped);
+        assert_eq!(facility.key(&start), facility.key(&swapped));
+
+        assert!(Facility::parse("The first floor contains a hydrogen generator.").is_err());
+        assert!(Facility::parse("The fifth floor contains nothing relevant.").is_err());
     }
 }
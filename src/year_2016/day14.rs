@@ -1,89 +1,91 @@
 use crate::util::input::get_input_string;
+use std::collections::VecDeque;
 
-use anyhow::Result;
-use md5;
+use anyhow::{Context, Result};
+use itertools::Itertools;
 
 use crate::day::Day;
+use crate::util::hashmine::{mine, nibble, to_hex, Salted};
 
-fn calculate_md5_hash<'a>(
-    cache: &'a mut Vec<String>,
-    index: usize,
-    salt: &str,
-    iterations: usize,
-) -> &'a str {
-    while index >= cache.len() {
-        let i = cache.len();
-        let mut hash = format!("{salt}{i}");
-        for _ in 0..iterations {
-            hash = format!("{:x}", md5::compute(hash));
-        }
-        cache.push(hash);
+// How far past a triple to look for a matching quintuple
+const LOOKAHEAD: usize = 1000;
+
+// The digest after rehashing the hex form iterations - 1 more times
+fn stretched_digest(salted: &Salted, index: usize, iterations: usize) -> [u8; 16] {
+    let mut digest = salted.digest(index);
+    for _ in 1..iterations {
+        digest = md5::compute(to_hex(&digest)).0;
     }
-    &cache[index]
+    digest
 }
 
-fn get_triple_char(s: &str) -> Option<char> {
-    let mut chars = s.chars().peekable();
-    while let Some(ch) = chars.next() {
-        let mut count = 1;
-        while let Some(next) = chars.peek() {
-            if *next == ch {
-                count += 1;
-                chars.next();
-            } else {
-                break;
+// All that's kept of each hash: the digit of its first triple, and a bitmask of the
+// digits that appear five times in a row
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+struct Features {
+    triple: Option<u8>,
+    quintuples: u16,
+}
+
+impl Features {
+    fn from_digest(digest: &[u8; 16]) -> Self {
+        let mut features = Features::default();
+        for (digit, run) in &(0..32).map(|i| nibble(digest, i)).group_by(|d| *d) {
+            let length = run.count();
+            if length >= 3 && features.triple.is_none() {
+                features.triple = Some(digit);
+            }
+            if length >= 5 {
+                features.quintuples |= 1 << digit;
             }
         }
-        if count >= 3 {
-            return Some(ch);
-        }
+        features
     }
-    None
 }
 
-fn check_five(s: &str, ch: char) -> bool {
-    let mut chars = s.chars().peekable();
-    while let Some(c) = chars.next() {
-        if c == ch {
-            let mut count = 1;
-            while let Some(next) = chars.peek() {
-                if *next == ch {
-                    count += 1;
-                    chars.next();
-                } else {
-                    break;
-                }
-            }
-            if count >= 5 {
-                return true;
-            }
+// Adds (or removes) a hash's quintuples to the per digit counts
+fn tally(counts: &mut [isize; 16], features: &Features, delta: isize) {
+    for (digit, count) in counts.iter_mut().enumerate() {
+        if features.quintuples & (1 << digit) != 0 {
+            *count += delta;
         }
     }
-    false
 }
 
-fn index_of_64th(salt: &str, iterations: usize) -> usize {
+// Indices of the first count keys. Each hash is computed once, ahead of time and in
+// parallel, and only the window from a candidate to its last lookahead hash is kept.
+fn pad_keys(salt: &str, iterations: usize, count: usize) -> Result<Vec<usize>> {
+    let salted = Salted::new(salt);
+    let mut hashes = mine(0, |i| {
+        Some(Features::from_digest(&stretched_digest(
+            &salted, i, iterations,
+        )))
+    })
+    .map(|(_, features)| features);
+    // Holds index to index + LOOKAHEAD, with the quintuples counted for all but the first
+    let mut window: VecDeque<Features> = hashes.by_ref().take(LOOKAHEAD + 1).collect();
+    let mut quintuples = [0isize; 16];
+    for features in window.iter().skip(1) {
+        tally(&mut quintuples, features, 1);
+    }
+    let mut keys = Vec::new();
     let mut index = 0;
-    let mut pad_key_count = 0;
-    let mut hash_cache: Vec<String> = Vec::new();
-    loop {
-        let hash = calculate_md5_hash(&mut hash_cache, index, salt, iterations);
-        if let Some(ch) = get_triple_char(hash) {
-            let start = index + 1;
-            for i in start..start + 1000 {
-                let h = calculate_md5_hash(&mut hash_cache, i, salt, iterations);
-                if check_five(h, ch) {
-                    pad_key_count += 1;
-                    break;
-                }
-            }
-        }
-        if pad_key_count == 64 {
-            break;
+    while keys.len() < count {
+        let candidate = window.pop_front().with_context(|| "Empty hash window")?;
+        if candidate
+            .triple
+            .is_some_and(|digit| quintuples[digit as usize] > 0)
+        {
+            keys.push(index);
         }
+        let front = window.front().with_context(|| "Empty hash window")?;
+        tally(&mut quintuples, front, -1);
+        let next = hashes.next().with_context(|| "Ran out of hashes")?;
+        tally(&mut quintuples, &next, 1);
+        window.push_back(next);
         index += 1;
     }
-    index
+    Ok(keys)
 }
 
 pub struct Day14;
@@ -92,10 +94,42 @@ impl Day for Day14 {
     fn main() -> Result<()> {
         let input = get_input_string(2016, 14)?;
         let salt = input.trim();
-        let index = index_of_64th(salt, 1);
-        println!("Index {index} generates the 64th one-time pad key");
-        let index = index_of_64th(salt, 2017);
-        println!("Index {index} generates the 64th one-time pad key when using stretched hashing");
+        for (iterations, description) in [(1, ""), (2017, " when using stretched hashing")] {
+            let keys = pad_keys(salt, iterations, 64)?;
+            let index = keys.last().with_context(|| "No keys")?;
+            println!("Index {index} generates the 64th one-time pad key{description}");
+            println!("All key indices: {}", keys.iter().join(", "));
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example_day_2016_14() {
+        let keys = pad_keys("abc", 1, 64).unwrap();
+        assert_eq!(64, keys.len());
+        assert_eq!([39, 92], keys[..2]);
+        assert_eq!(Some(&22728), keys.last());
+        let stretched = pad_keys("abc", 2017, 1).unwrap();
+        assert_eq!(vec![10], stretched);
+    }
+
+    #[test]
+    fn test_features() {
+        let salted = Salted::new("abc");
+        assert_eq!(
+            "a107ff634856bb300138cac6568c0f24".as_bytes(),
+            to_hex(&stretched_digest(&salted, 0, 2017))
+        );
+        // abc18 contains cc38887a5, abc816 contains eeeee
+        let features = Features::from_digest(&salted.digest(18));
+        assert_eq!(Some(8), features.triple);
+        assert_eq!(0, features.quintuples);
+        let features = Features::from_digest(&salted.digest(816));
+        assert_eq!(1 << 0xe, features.quintuples);
+    }
+}
@@ -5,21 +5,16 @@ use anyhow::{bail, Context, Result};
 use lazy_regex::regex_captures;
 
 use crate::day::Day;
+use crate::util::math::crt;
 
+// Disc c (0 indexed) is reached at time + c + 1, where it must be at position 0
 fn find_drop_time(discs: &[(usize, usize)]) -> Option<usize> {
-    for time in 0..=usize::MAX {
-        let mut pass = true;
-        for (c, (pos, init)) in discs.iter().enumerate() {
-            if (c + 1 + init + time) % pos != 0 {
-                pass = false;
-                break;
-            }
-        }
-        if pass {
-            return Some(time);
-        }
-    }
-    None
+    let congruences: Vec<(i64, u64)> = discs
+        .iter()
+        .enumerate()
+        .map(|(c, (pos, init))| (-((c + 1 + init) as i64), *pos as u64))
+        .collect();
+    crt(&congruences).and_then(|(time, _)| time.try_into().ok())
 }
 
 pub struct Day15;
@@ -192,34 +192,100 @@ impl<'a> IntoIterator for &'a Disk<'a> {
     }
 }
 
+// Parity of the ones at the start of a disk filled from the source, without filling it. The
+// disk is the source and its reversed inverse taking turns, with the dragon curve's bits
+// between them.
+struct DragonParity {
+    // Parity of the first n bits of the source, for each n
+    source_prefix: Vec<bool>,
+}
+
+impl DragonParity {
+    fn new(source: &[Digit]) -> Self {
+        let mut source_prefix = vec![false];
+        for digit in source {
+            let last = source_prefix[source_prefix.len() - 1];
+            source_prefix.push(last ^ (*digit == Digit::One));
+        }
+        DragonParity { source_prefix }
+    }
+
+    // Parity of the first n bits of the reversed inverse of the source
+    fn reversed_prefix(&self, n: usize) -> bool {
+        let len = self.source_prefix.len() - 1;
+        self.source_prefix[len] ^ self.source_prefix[len - n] ^ (n % 2 == 1)
+    }
+
+    // The kth joining bit (counting from 1) is 1 when the odd part of k is 3 mod 4
+    fn joiner_ones(count: usize) -> usize {
+        let mut ones = 0;
+        let mut halved = count;
+        while halved > 0 {
+            ones += (halved + 1) / 4;
+            halved /= 2;
+        }
+        ones
+    }
+
+    fn ones_before(&self, n: usize) -> bool {
+        let len = self.source_prefix.len() - 1;
+        // Each period is a copy of the source or its reversed inverse, then a joining bit
+        let (periods, rest) = (n / (len + 1), n % (len + 1));
+        let originals = periods.div_ceil(2);
+        let reversed = periods / 2;
+        let whole = (originals % 2 == 1 && self.source_prefix[len])
+            ^ (reversed % 2 == 1 && self.reversed_prefix(len))
+            ^ (DragonParity::joiner_ones(periods) % 2 == 1);
+        let partial = if periods % 2 == 0 {
+            self.source_prefix[rest]
+        } else {
+            self.reversed_prefix(rest)
+        };
+        whole ^ partial
+    }
+}
+
+// Each checksum digit covers a block of the disk, and is 1 when the block has an even
+// number of ones
+fn fast_checksum(source: &[Digit], len: usize) -> Vec<Digit> {
+    let mut digits = len / 2;
+    if digits == 0 {
+        return Vec::new();
+    }
+    let mut block = 2;
+    while digits.is_multiple_of(2) {
+        digits /= 2;
+        block *= 2;
+    }
+    let parity = DragonParity::new(source);
+    (0..digits)
+        .map(|i| {
+            if parity.ones_before(i * block) == parity.ones_before((i + 1) * block) {
+                Digit::One
+            } else {
+                Digit::Zero
+            }
+        })
+        .collect()
+}
+
+fn to_string(digits: &[Digit]) -> String {
+    digits.iter().map(|d| d.to_char()).collect()
+}
+
 pub struct Day16;
 
 impl Day for Day16 {
     fn main() -> Result<()> {
         let input: Vec<Digit> = (get_input_string(2016, 16)?)
             .chars()
-            .filter(|ch| *ch == '1' || *ch == '0')
             .filter_map(|ch| Digit::from_char(&ch))
             .collect();
-        let disk1_checksum: String = Disk::new(&input, 272)
-            .fill()
-            .checksum()
-            .iter()
-            .map(|d| d.to_char())
-            .collect();
-        println!("Disk 1 Checksum: '{disk1_checksum}'");
-        // TAKES TOO LONG TO COMPUTE PART 2, NEED TO RETHINK
-        // NOTE: I think it is the recalculate the length on each step that is
-        // killing us. Keep a running total via total = (total * 2) + 1; Can't
-        // believe I missed that...
-        // NOTE: Tried that, it probably helps, but is insufficient speedup
-        let disk2_checksum: String = Disk::new(&input, 35651584)
-            .fill()
-            .checksum()
-            .iter()
-            .map(|d| d.to_char())
-            .collect();
-        println!("Disk 2 Checksum: '{disk2_checksum}");
+        let disk1_checksum = Disk::new(&input, 272).fill().checksum();
+        println!("Disk 1 Checksum: '{}'", to_string(&disk1_checksum));
+        // Too big to fill, so the checksum comes from block parities instead
+        let disk2_checksum = fast_checksum(&input, 35651584);
+        println!("Disk 2 Checksum: '{}'", to_string(&disk2_checksum));
         Ok(())
     }
 }
@@ -227,6 +293,7 @@ impl Day for Day16 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::util::random::generator;
 
     #[test]
     fn example_1_day_2016_16() {
@@ -284,4 +351,65 @@ mod tests {
             .collect();
         assert_eq!("1111000010100101011110000", single_step);
     }
+
+    #[test]
+    fn example_checksum_day_2016_16() {
+        let source: Vec<Digit> = "10000"
+            .chars()
+            .filter_map(|c| Digit::from_char(&c))
+            .collect();
+        assert_eq!(
+            "01100",
+            to_string(&Disk::new(&source, 20).fill().checksum())
+        );
+        assert_eq!("01100", to_string(&fast_checksum(&source, 20)));
+    }
+
+    #[test]
+    fn test_fast_checksum_matches_disk() {
+        let mut next = generator(16);
+        for _ in 0..40 {
+            let source_len = 1 + (next() % 12) as usize;
+            let source: Vec<Digit> = (0..source_len)
+                .map(|_| {
+                    if next().is_multiple_of(2) {
+                        Digit::Zero
+                    } else {
+                        Digit::One
+                    }
+                })
+                .collect();
+            for len in (2..300).step_by(1 + (next() % 7) as usize) {
+                let disk = to_string(&Disk::new(&source, len).fill().checksum());
+                let fast = to_string(&fast_checksum(&source, len));
+                assert_eq!(disk, fast, "{} to {len}", to_string(&source));
+            }
+        }
+        assert!(fast_checksum(&[Digit::One], 1).is_empty());
+    }
+
+    #[test]
+    fn test_puzzle_sized_disk() {
+        let source: Vec<Digit> = "10111011111001111"
+            .chars()
+            .filter_map(|c| Digit::from_char(&c))
+            .collect();
+        assert_eq!("11101010111100010", to_string(&fast_checksum(&source, 272)));
+        assert_eq!(
+            "01001101001000101",
+            to_string(&fast_checksum(&source, 35651584))
+        );
+    }
+
+    #[test]
+    fn test_joiner_bits() {
+        // The dragon curve's joining bits 001001100011011
+        let joiners = [0, 0, 1, 0, 0, 1, 1, 0, 0, 0, 1, 1, 0, 1, 1];
+        for count in 0..=joiners.len() {
+            assert_eq!(
+                joiners[..count].iter().sum::<usize>(),
+                DragonParity::joiner_ones(count)
+            );
+        }
+    }
 }
@@ -0,0 +1,80 @@
+use crate::util::input::get_input_string;
+
+use anyhow::Result;
+
+use crate::day::Day;
+use crate::year_2016::assembunny::{parse_program, Instruction, Machine};
+
+fn safe_value(program: &[Instruction], eggs: i64) -> Result<i64> {
+    let mut machine = Machine::optimized(program).with_register('a', eggs)?;
+    machine.run(None)?;
+    machine.register('a')
+}
+
+pub struct Day23;
+
+impl Day for Day23 {
+    fn main() -> Result<()> {
+        let program = parse_program(&get_input_string(2016, 23)?)?;
+        println!("Value sent to the safe: {}", safe_value(&program, 7)?);
+        println!(
+            "Value sent to the safe with 12 eggs: {}",
+            safe_value(&program, 12)?
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example_day_2016_23() {
+        let program = parse_program("cpy 2 a\ntgl a\ntgl a\ntgl a\ncpy 1 a\ndec a\ndec a").unwrap();
+        assert_eq!(3, safe_value(&program, 0).unwrap());
+    }
+
+    // Same shape as the puzzle input: a factorial built from multiply loops, which tgl
+    // then rewrites into an addition of 73 * 71
+    const FACTORIAL: &str = "cpy a b\ndec b\ncpy a d\ncpy 0 a\ncpy b c\ninc a\ndec c\njnz c -2\n\
+        dec d\njnz d -5\ndec b\ncpy b c\ncpy c d\ndec d\ninc c\njnz d -2\ntgl c\ncpy -16 c\n\
+        jnz 1 c\ncpy 73 c\njnz 71 d\ninc a\ninc d\njnz d -2\ninc c\njnz c -5";
+
+    #[test]
+    fn test_optimized_matches_plain() {
+        let program = parse_program(FACTORIAL).unwrap();
+        let mut plain = Machine::new(&program).with_register('a', 7).unwrap();
+        let mut optimized = Machine::optimized(&program).with_register('a', 7).unwrap();
+        plain.run(None).unwrap();
+        optimized.run(None).unwrap();
+        assert_eq!(5040 + 73 * 71, plain.register('a').unwrap());
+        assert_eq!(plain.vm.registers, optimized.vm.registers);
+        assert!(optimized.vm.steps * 100 < plain.vm.steps);
+        assert_eq!(479001600 + 73 * 71, safe_value(&program, 12).unwrap());
+    }
+
+    // cargo test --release -- --ignored --nocapture bench
+    #[test]
+    #[ignore]
+    fn bench_optimizer() {
+        let program = parse_program(FACTORIAL).unwrap();
+        for eggs in [8, 9, 10] {
+            let machines = [
+                ("plain", Machine::new(&program)),
+                ("optimized", Machine::optimized(&program)),
+            ];
+            for (label, machine) in machines {
+                let mut machine = machine.with_register('a', eggs).unwrap();
+                let start = std::time::Instant::now();
+                machine.run(None).unwrap();
+                println!(
+                    "a = {eggs}, {label}: {} in {} steps, {:?}",
+                    machine.register('a').unwrap(),
+                    machine.vm.steps,
+                    start.elapsed()
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use crate::util::input::get_input_string;
+
+use anyhow::{Context, Result};
+
+use crate::day::Day;
+use crate::year_2016::assembunny::{parse_program, Instruction, Machine};
+
+const SIGNAL_LENGTH: usize = 32;
+const STEP_LIMIT: usize = 10_000_000;
+
+fn expected_signal(index: usize) -> i64 {
+    (index % 2) as i64
+}
+
+fn produces_clock(program: &[Instruction], a: i64) -> Result<bool> {
+    let mut machine = Machine::optimized(program).with_register('a', a)?;
+    machine.run_until(
+        |m| {
+            m.output.len() >= SIGNAL_LENGTH
+                || m.output
+                    .last()
+                    .is_some_and(|v| *v != expected_signal(m.output.len() - 1))
+        },
+        Some(STEP_LIMIT),
+    )?;
+    let output = &machine.vm.output;
+    Ok(output.len() >= SIGNAL_LENGTH
+        && output
+            .iter()
+            .enumerate()
+            .all(|(i, v)| *v == expected_signal(i)))
+}
+
+fn lowest_clock_seed(program: &[Instruction], max: i64) -> Result<Option<i64>> {
+    for a in 1..=max {
+        if produces_clock(program, a)? {
+            return Ok(Some(a));
+        }
+    }
+    Ok(None)
+}
+
+pub struct Day25;
+
+impl Day for Day25 {
+    fn main() -> Result<()> {
+        let program = parse_program(&get_input_string(2016, 25)?)?;
+        let seed = lowest_clock_seed(&program, 1_000_000)?
+            .with_context(|| "Should find a seed that produces a clock signal")?;
+        println!("Lowest value for 'a' producing a clock signal: {seed}");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lowest_clock_seed() {
+        let program =
+            parse_program("cpy a b\ndec b\ndec b\nout b\ninc b\nout b\ndec b\njnz 1 -4").unwrap();
+        assert!(!produces_clock(&program, 1).unwrap());
+        assert!(produces_clock(&program, 2).unwrap());
+        assert_eq!(Some(2), lowest_clock_seed(&program, 10).unwrap());
+    }
+}
@@ -0,0 +1,431 @@
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+
+use anyhow::{bail, Context, Result};
+
+// Longest block the encoder looks for repeats of
+#[cfg(test)]
+const MAX_BLOCK: usize = 32;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Version {
+    // Markers inside repeated data are copied literally
+    V1,
+    // Markers inside repeated data are expanded too
+    V2,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Node {
+    // A span of the compressed input that's copied as is
+    Literal(Range<usize>),
+    Repeat {
+        times: u64,
+        children: Vec<Node>,
+        // Of a single copy of the children
+        length: u64,
+    },
+}
+
+impl Node {
+    fn repeat(times: u64, children: Vec<Node>) -> Result<Self> {
+        let length = total_length(&children)?;
+        Ok(Node::Repeat {
+            times,
+            children,
+            length,
+        })
+    }
+
+    fn len(&self) -> Result<u64> {
+        match self {
+            Node::Literal(range) => Ok(range.len() as u64),
+            Node::Repeat { times, length, .. } => length
+                .checked_mul(*times)
+                .with_context(|| "Decompressed length overflows"),
+        }
+    }
+}
+
+fn total_length(nodes: &[Node]) -> Result<u64> {
+    nodes.iter().try_fold(0u64, |total, node| {
+        total
+            .checked_add(node.len()?)
+            .with_context(|| "Decompressed length overflows")
+    })
+}
+
+// Reads the digits starting at pos, returning the number and the position after it
+fn read_number(input: &[u8], pos: usize, end: usize, marker: usize) -> Result<(u64, usize)> {
+    if pos >= end {
+        bail!("Marker at {marker} is unterminated");
+    }
+    let digits = input[pos..end]
+        .iter()
+        .take_while(|b| b.is_ascii_digit())
+        .count();
+    if digits == 0 {
+        bail!("Marker at {marker}: expected a number at {pos}");
+    }
+    let mut num: u64 = 0;
+    for b in &input[pos..pos + digits] {
+        num = num
+            .checked_mul(10)
+            .and_then(|n| n.checked_add(u64::from(b - b'0')))
+            .with_context(|| format!("Marker at {marker}: number at {pos} is too large"))?;
+    }
+    Ok((num, pos + digits))
+}
+
+fn expect_byte(input: &[u8], pos: usize, end: usize, byte: u8, marker: usize) -> Result<usize> {
+    match input[..end].get(pos) {
+        Some(b) if *b == byte => Ok(pos + 1),
+        Some(_) => bail!("Marker at {marker}: expected '{}' at {pos}", byte as char),
+        None => bail!("Marker at {marker} is unterminated"),
+    }
+}
+
+// Parses the (AxB) marker at start, returning the repeated data's range and the times
+fn read_marker(input: &[u8], start: usize, end: usize) -> Result<(Range<usize>, u64)> {
+    let (length, pos) = read_number(input, start + 1, end, start)?;
+    let pos = expect_byte(input, pos, end, b'x', start)?;
+    let (times, pos) = read_number(input, pos, end, start)?;
+    let pos = expect_byte(input, pos, end, b')', start)?;
+    let available = end - pos;
+    let length = usize::try_from(length)
+        .ok()
+        .filter(|length| *length <= available)
+        .with_context(|| {
+            format!("Marker at {start} repeats {length} characters but only {available} follow")
+        })?;
+    Ok((pos..pos + length, times))
+}
+
+fn parse_nodes(input: &[u8], range: Range<usize>, version: Version) -> Result<Vec<Node>> {
+    let mut nodes = Vec::new();
+    let mut literal_start = range.start;
+    let mut pos = range.start;
+    while pos < range.end {
+        if input[pos] != b'(' {
+            pos += 1;
+            continue;
+        }
+        if literal_start < pos {
+            nodes.push(Node::Literal(literal_start..pos));
+        }
+        let (data, times) = read_marker(input, pos, range.end)?;
+        pos = data.end;
+        let children = match version {
+            Version::V1 if data.is_empty() => vec![],
+            Version::V1 => vec![Node::Literal(data)],
+            Version::V2 => parse_nodes(input, data, version)?,
+        };
+        nodes.push(Node::repeat(times, children)?);
+        literal_start = pos;
+    }
+    if literal_start < range.end {
+        nodes.push(Node::Literal(literal_start..range.end));
+    }
+    Ok(nodes)
+}
+
+// Marker-compressed text, checked up front so it can be decompressed lazily
+#[derive(Clone, Debug)]
+pub struct Compressed<'a> {
+    input: &'a [u8],
+    nodes: Vec<Node>,
+    len: u64,
+}
+
+impl<'a> Compressed<'a> {
+    pub fn parse(input: &'a str, version: Version) -> Result<Self> {
+        let input = input.as_bytes();
+        let nodes = parse_nodes(input, 0..input.len(), version)?;
+        let len = total_length(&nodes)?;
+        Ok(Compressed { input, nodes, len })
+    }
+
+    pub fn decompressed_len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn bytes(&self) -> Bytes<'_> {
+        Bytes {
+            input: self.input,
+            stack: vec![Frame {
+                nodes: &self.nodes,
+                next: 0,
+                repeats: 1,
+            }],
+            literal: 0..0,
+        }
+    }
+
+    // The decompressed byte at offset, without decompressing what comes before it
+    pub fn byte_at(&self, mut offset: u64) -> Option<u8> {
+        let mut nodes = self.nodes.as_slice();
+        'descend: loop {
+            for node in nodes {
+                let len = node.len().ok()?;
+                if offset >= len {
+                    offset -= len;
+                    continue;
+                }
+                match node {
+                    Node::Literal(range) => return Some(self.input[range.start + offset as usize]),
+                    Node::Repeat {
+                        children, length, ..
+                    } => {
+                        offset %= length;
+                        nodes = children;
+                        continue 'descend;
+                    }
+                }
+            }
+            return None;
+        }
+    }
+
+    // Streams the decompressed output, returning how many bytes were written
+    pub fn write_to<W: Write>(&self, sink: W) -> io::Result<u64> {
+        let mut sink = BufWriter::new(sink);
+        let mut written = 0;
+        for byte in self.bytes() {
+            sink.write_all(&[byte])?;
+            written += 1;
+        }
+        sink.flush()?;
+        Ok(written)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Frame<'a> {
+    nodes: &'a [Node],
+    next: usize,
+    repeats: u64,
+}
+
+// The decompressed output, one byte at a time
+#[derive(Clone, Debug)]
+pub struct Bytes<'a> {
+    input: &'a [u8],
+    stack: Vec<Frame<'a>>,
+    // What's left of the literal being copied
+    literal: Range<usize>,
+}
+
+impl Iterator for Bytes<'_> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        loop {
+            if let Some(pos) = self.literal.next() {
+                return Some(self.input[pos]);
+            }
+            let frame = self.stack.last_mut()?;
+            let nodes = frame.nodes;
+            let Some(node) = nodes.get(frame.next) else {
+                if frame.repeats > 1 {
+                    frame.repeats -= 1;
+                    frame.next = 0;
+                } else {
+                    self.stack.pop();
+                }
+                continue;
+            };
+            frame.next += 1;
+            match node {
+                Node::Literal(range) => self.literal = range.clone(),
+                Node::Repeat {
+                    times, children, ..
+                } => {
+                    if *times > 0 {
+                        self.stack.push(Frame {
+                            nodes: children,
+                            next: 0,
+                            repeats: *times,
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+// The (block length, times) that saves the most characters at the start of data, if any
+#[cfg(test)]
+fn best_repeat(data: &[u8]) -> Option<(usize, usize)> {
+    let mut best = None;
+    let mut best_saving = 0;
+    for length in 1..=MAX_BLOCK.min(data.len() / 2) {
+        let block = &data[..length];
+        let times = data
+            .chunks_exact(length)
+            .take_while(|c| *c == block)
+            .count();
+        let marker = format!("({length}x{times})").len();
+        let saving = (length * times).saturating_sub(marker + length);
+        if times > 1 && saving > best_saving {
+            best = Some((length, times));
+            best_saving = saving;
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+fn encode_into(data: &[u8], version: Version, out: &mut Vec<u8>) -> Result<()> {
+    let mut pos = 0;
+    while pos < data.len() {
+        if let Some((length, times)) = best_repeat(&data[pos..]) {
+            let block = &data[pos..pos + length];
+            let mut encoded = Vec::new();
+            match version {
+                Version::V1 => encoded.extend_from_slice(block),
+                Version::V2 => encode_into(block, version, &mut encoded)?,
+            }
+            write!(out, "({}x{times})", encoded.len())?;
+            out.extend(encoded);
+            pos += length * times;
+        } else if data[pos] == b'(' {
+            // Only version 1 can copy a marker start without expanding it
+            match version {
+                Version::V1 => out.extend_from_slice(b"(1x1)("),
+                Version::V2 => bail!("Can't encode '(' at {pos} for version 2"),
+            }
+            pos += 1;
+        } else {
+            out.push(data[pos]);
+            pos += 1;
+        }
+    }
+    Ok(())
+}
+
+// Compresses text so that decompressing it with the same version gives it back, for round
+// trip tests
+#[cfg(test)]
+pub fn encode(text: &str, version: Version) -> Result<String> {
+    let mut out = Vec::new();
+    encode_into(text.as_bytes(), version, &mut out)?;
+    Ok(String::from_utf8(out)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decompress(input: &str, version: Version) -> String {
+        let compressed = Compressed::parse(input, version).unwrap();
+        let output = String::from_utf8(compressed.bytes().collect()).unwrap();
+        assert_eq!(compressed.decompressed_len(), output.len() as u64);
+        output
+    }
+
+    #[test]
+    fn test_version_1() {
+        for (input, output) in [
+            ("ADVENT", "ADVENT"),
+            ("A(1x5)BC", "ABBBBBC"),
+            ("(3x3)XYZ", "XYZXYZXYZ"),
+            ("A(2x2)BCD(2x2)EFG", "ABCBCDEFEFG"),
+            ("(6x1)(1x3)A", "(1x3)A"),
+            ("X(8x2)(3x3)ABCY", "X(3x3)ABC(3x3)ABCY"),
+            ("(0x5)A(2x0)BC", "A"),
+        ] {
+            assert_eq!(output, decompress(input, Version::V1));
+        }
+    }
+
+    #[test]
+    fn test_version_2() {
+        assert_eq!("XYZXYZXYZ", decompress("(3x3)XYZ", Version::V2));
+        assert_eq!(
+            "XABCABCABCABCABCABCY",
+            decompress("X(8x2)(3x3)ABCY", Version::V2)
+        );
+        for (input, len) in [
+            ("(27x12)(20x12)(13x14)(7x10)(1x12)A", 241920),
+            (
+                "(25x3)(3x3)ABC(2x3)XY(5x2)PQRSTX(18x9)(3x2)TWO(5x7)SEVEN",
+                445,
+            ),
+        ] {
+            let compressed = Compressed::parse(input, Version::V2).unwrap();
+            assert_eq!(len, compressed.decompressed_len());
+            assert_eq!(len, compressed.bytes().count() as u64);
+        }
+    }
+
+    #[test]
+    fn test_sampling_and_sink() {
+        let input = "AB(25x3)(3x3)ABC(2x3)XY(5x2)PQRSTX(18x9)(3x2)TWO(5x7)SEVENZ";
+        let compressed = Compressed::parse(input, Version::V2).unwrap();
+        let output: Vec<u8> = compressed.bytes().collect();
+        for (offset, byte) in output.iter().enumerate() {
+            assert_eq!(Some(*byte), compressed.byte_at(offset as u64));
+        }
+        assert_eq!(None, compressed.byte_at(output.len() as u64));
+        let mut sink = Vec::new();
+        assert_eq!(output.len() as u64, compressed.write_to(&mut sink).unwrap());
+        assert_eq!(output, sink);
+    }
+
+    #[test]
+    fn test_malformed_markers() {
+        for (input, version, message) in [
+            ("AB(3x", Version::V1, "Marker at 2 is unterminated"),
+            ("(x3)A", Version::V1, "Marker at 0: expected a number at 1"),
+            ("A(3y3)ABC", Version::V1, "Marker at 1: expected 'x' at 3"),
+            ("(3x3]ABC", Version::V1, "Marker at 0: expected ')' at 4"),
+            (
+                "AB(5x2)XY",
+                Version::V1,
+                "Marker at 2 repeats 5 characters but only 2 follow",
+            ),
+            // Version 2 checks markers inside repeated data, which must fit inside it
+            (
+                "(7x2)A(3x2)BC",
+                Version::V2,
+                "Marker at 6 repeats 3 characters but only 1 follow",
+            ),
+            ("(3x2)(1x", Version::V2, "Marker at 5 is unterminated"),
+        ] {
+            let error = Compressed::parse(input, version).unwrap_err();
+            assert_eq!(message, error.to_string());
+        }
+        assert!(Compressed::parse("(3x2)(1x", Version::V1).is_ok());
+        assert!(Compressed::parse("(99999999999999999999x2)", Version::V1).is_err());
+        let nested = "(30x4294967296)(15x4294967296)(1x4294967296)A";
+        let error = Compressed::parse(nested, Version::V2).unwrap_err();
+        assert_eq!("Decompressed length overflows", error.to_string());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for text in [
+            "",
+            "ADVENT",
+            "AAAAAAAAAAAAAAAAAAAAB",
+            "XYZXYZXYZXYZXYZXYZXYZQ",
+            "ABABABABABCDCDCDCDCDABABABABABCDCDCDCDCDABABABABABCDCDCDCDCD",
+            "no markers, just a sentence with some spaces    and repeats repeats repeats",
+        ] {
+            for version in [Version::V1, Version::V2] {
+                let encoded = encode(text, version).unwrap();
+                assert!(encoded.len() <= text.len());
+                assert_eq!(text, decompress(&encoded, version));
+            }
+        }
+        let text = "(1x3)A(((((((((((B";
+        let encoded = encode(text, Version::V1).unwrap();
+        assert_eq!(text, decompress(&encoded, Version::V1));
+        assert!(encode(text, Version::V2).is_err());
+        // Version 2 nests markers to compress repeats of repeats
+        let text = "AAAAAAAAAAB".repeat(10);
+        assert!(
+            encode(&text, Version::V2).unwrap().len() < encode(&text, Version::V1).unwrap().len()
+        );
+    }
+}
@@ -5,6 +5,7 @@ use std::ops::Range;
 
 use anyhow::{bail, Context, Result};
 
+use crate::data::RangeSet;
 use crate::day::Day;
 
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
@@ -54,13 +55,6 @@ enum Phase {
     ProdMap(ProdMapName),
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-enum Overlap {
-    Before,
-    After,
-    Inside,
-}
-
 #[derive(Copy, Clone, Debug)]
 struct ProdRange {
     destination: u64,
@@ -68,10 +62,6 @@ struct ProdRange {
     length: u64,
 }
 
-fn range_len(range: &Range<u64>) -> u64 {
-    range.end - range.start
-}
-
 impl ProdRange {
     fn parse(line: &str) -> Result<Self> {
         let mut parts = line.split(" ");
@@ -100,53 +90,12 @@ impl ProdRange {
         })
     }
 
-    fn overlap(&self, val: u64) -> Overlap {
-        if val >= self.source {
-            if val < self.source + self.length {
-                Overlap::Inside
-            } else {
-                Overlap::After
-            }
-        } else {
-            Overlap::Before
-        }
+    fn source(&self) -> RangeSet<u64> {
+        std::iter::once(self.source..self.source + self.length).collect()
     }
 
-    // Returns (updated ranges, preserved ranges)
-    fn map(&self, range: Range<u64>) -> Result<(Vec<Range<u64>>, Vec<Range<u64>>)> {
-        Ok(
-            match (self.overlap(range.start), self.overlap(range.end - 1)) {
-                (Overlap::Before, Overlap::Before) => (Vec::new(), vec![range]),
-                (Overlap::After, Overlap::After) => (Vec::new(), vec![range]),
-                (Overlap::Inside, Overlap::Inside) => {
-                    let new_start = range.start - self.source + self.destination;
-                    (vec![new_start..new_start + range_len(&range)], Vec::new())
-                }
-                (Overlap::Before, Overlap::After) => {
-                    // split into 3 parts
-                    let left = range.start..self.source;
-                    let middle = self.destination..self.destination + self.length;
-                    let right = self.source + self.length..range.end;
-                    (vec![middle], vec![left, right])
-                }
-                (Overlap::Before, Overlap::Inside) => {
-                    // split into 2 parts
-                    let left = range.start..self.source;
-                    let middle =
-                        self.destination..self.destination + range_len(&range) - range_len(&left);
-                    (vec![middle], vec![left])
-                }
-                (Overlap::Inside, Overlap::After) => {
-                    // split into 2 parts
-                    let middle_start = range.start - self.source + self.destination;
-                    let middle_end = self.destination + self.length;
-                    let middle = middle_start..middle_end;
-                    let right = self.source + self.length..range.end;
-                    (vec![middle], vec![right])
-                }
-                _ => bail!("Range  {range:?} makes no sense against {self:?}"),
-            },
-        )
+    fn shift(&self, range: &Range<u64>) -> Range<u64> {
+        range.start - self.source + self.destination..range.end - self.source + self.destination
     }
 }
 
@@ -156,20 +105,17 @@ struct ProdMap {
 }
 
 impl ProdMap {
-    fn map(&self, val: Range<u64>) -> Result<Vec<Range<u64>>> {
-        let mut processing: Vec<Range<u64>> = vec![val];
-        let mut resolved: Vec<Range<u64>> = Vec::new();
+    fn map(&self, val: &RangeSet<u64>) -> RangeSet<u64> {
+        let mut processing = val.clone();
+        let mut resolved: RangeSet<u64> = RangeSet::new();
         for range in &self.ranges {
-            let mut next_processing = Vec::new();
-            for proc in processing {
-                let (mut done, mut reproc) = range.map(proc)?;
-                next_processing.append(&mut reproc);
-                resolved.append(&mut done);
+            let source = range.source();
+            for moved in processing.intersection(&source).iter() {
+                resolved.insert(range.shift(moved));
             }
-            processing = next_processing;
+            processing = processing.difference(&source);
         }
-        resolved.append(&mut processing);
-        Ok(resolved)
+        resolved.union(&processing)
     }
 
     fn push(&mut self, range: ProdRange) {
@@ -193,80 +139,123 @@ impl ProdMaps {
         map.push(range);
     }
 
-    fn map(&self, seeds: Range<u64>) -> Result<Vec<Range<u64>>> {
-        let mut res = vec![seeds];
+    fn map(&self, seeds: &RangeSet<u64>) -> RangeSet<u64> {
+        let mut res = seeds.clone();
         let mut next = Some(ProdMapName::first());
         while let Some(map_name) = next {
-            res = if let Some(map) = self.maps.get(&map_name) {
-                let mut new_res = Vec::new();
-                for seed_range in res {
-                    new_res.append(&mut map.map(seed_range)?);
-                }
-                new_res
-            } else {
-                res
-            };
+            if let Some(map) = self.maps.get(&map_name) {
+                res = map.map(&res);
+            }
             next = map_name.next();
         }
-        Ok(res)
+        res
     }
 }
 
-pub struct Day05;
-
-impl Day for Day05 {
-    fn main() -> Result<()> {
-        let input = BufReader::new(get_input(2023, 05)?);
-        let mut seeds: Vec<u64> = Vec::new();
-        let mut phase: Option<Phase> = Some(Phase::Seeds);
-        let mut maps = ProdMaps::default();
-        for line in input.lines().map(|l| l.unwrap()) {
-            if let Some(p) = phase {
-                match p {
-                    Phase::Seeds => {
-                        if line.starts_with("seeds: ") {
-                            for seed_str in line.split(" ").filter(|seg| *seg != "seeds:") {
-                                seeds.push(seed_str.parse().with_context(|| {
-                                    format!("Expected to parse seed from '{seed_str}'")
-                                })?);
-                            }
-                        } else if line.trim().is_empty() {
-                            phase = Some(Phase::ProdMap(ProdMapName::first()));
-                        } else {
-                            bail!("Unexpected line, expecting seeds");
-                        }
-                    }
-                    Phase::ProdMap(map_name) => {
-                        if line.trim().is_empty() {
-                            phase = map_name.next().map(|mn| Phase::ProdMap(mn));
-                        } else if !line.starts_with(map_name.expected_label()) {
-                            maps.push(map_name, ProdRange::parse(&line)?);
+fn parse_almanac<I: Iterator<Item = String>>(lines: I) -> Result<(Vec<u64>, ProdMaps)> {
+    let mut seeds: Vec<u64> = Vec::new();
+    let mut phase: Option<Phase> = Some(Phase::Seeds);
+    let mut maps = ProdMaps::default();
+    for line in lines {
+        if let Some(p) = phase {
+            match p {
+                Phase::Seeds => {
+                    if line.starts_with("seeds: ") {
+                        for seed_str in line.split(" ").filter(|seg| *seg != "seeds:") {
+                            seeds.push(seed_str.parse().with_context(|| {
+                                format!("Expected to parse seed from '{seed_str}'")
+                            })?);
                         }
+                    } else if line.trim().is_empty() {
+                        phase = Some(Phase::ProdMap(ProdMapName::first()));
+                    } else {
+                        bail!("Unexpected line, expecting seeds");
                     }
                 }
-            } else {
-                bail!("Unexpected extra line '{line}'");
-            }
-        }
-        let mut lowest_soil: u64 = u64::MAX;
-        for seed in &seeds {
-            for res in maps.map(*seed..seed + 1)? {
-                if res.start < lowest_soil {
-                    lowest_soil = res.start;
+                Phase::ProdMap(map_name) => {
+                    if line.trim().is_empty() {
+                        phase = map_name.next().map(Phase::ProdMap);
+                    } else if !line.starts_with(map_name.expected_label()) {
+                        maps.push(map_name, ProdRange::parse(&line)?);
+                    }
                 }
             }
+        } else {
+            bail!("Unexpected extra line '{line}'");
         }
+    }
+    Ok((seeds, maps))
+}
+
+fn lowest_locations(seeds: &[u64], maps: &ProdMaps) -> Result<(u64, u64)> {
+    let single_seeds: RangeSet<u64> = seeds.iter().map(|seed| *seed..seed + 1).collect();
+    let expanded_seeds: RangeSet<u64> = seeds
+        .chunks_exact(2)
+        .map(|pair| pair[0]..pair[0] + pair[1])
+        .collect();
+    Ok((
+        maps.map(&single_seeds)
+            .min()
+            .with_context(|| "Expected at least one seed")?,
+        maps.map(&expanded_seeds)
+            .min()
+            .with_context(|| "Expected at least one seed range")?,
+    ))
+}
+
+pub struct Day05;
+
+impl Day for Day05 {
+    fn main() -> Result<()> {
+        let input = BufReader::new(get_input(2023, 05)?);
+        let (seeds, maps) = parse_almanac(input.lines().map(|l| l.unwrap()))?;
+        let (lowest_soil, lowest_expanded_soil) = lowest_locations(&seeds, &maps)?;
         println!("Closest soil block to start at is: {lowest_soil}");
-        let mut expanded_seeds = seeds.iter();
-        let mut lowest_soil: u64 = u64::MAX;
-        while let (Some(start), Some(len)) = (expanded_seeds.next(), expanded_seeds.next()) {
-            for res in maps.map(*start..start + len)? {
-                if res.start < lowest_soil {
-                    lowest_soil = res.start;
-                }
-            }
-        }
-        println!("Closest soil block using expanded seeds is: {lowest_soil}");
+        println!("Closest soil block using expanded seeds is: {lowest_expanded_soil}");
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example_day_2023_05() {
+        let input = "seeds: 79 14 55 13
+
+seed-to-soil map:
+50 98 2
+52 50 48
+
+soil-to-fertilizer map:
+0 15 37
+37 52 2
+39 0 15
+
+fertilizer-to-water map:
+49 53 8
+0 11 42
+42 0 7
+57 7 4
+
+water-to-light map:
+88 18 7
+18 25 70
+
+light-to-temperature map:
+45 77 23
+81 45 19
+68 64 13
+
+temperature-to-humidity map:
+0 69 1
+1 0 69
+
+humidity-to-location map:
+60 56 37
+56 93 4";
+        let (seeds, maps) = parse_almanac(input.lines().map(String::from)).unwrap();
+        assert_eq!((35, 46), lowest_locations(&seeds, &maps).unwrap());
+    }
+}
@@ -3,44 +3,69 @@ use std::io::{BufRead, BufReader};
 
 use anyhow::{Context, Result};
 
+use crate::data::Point2D;
 use crate::day::Day;
+use crate::util::cardinal::{Direction, Turn};
 
-struct XmasCounter {
-    cache: [char; 4],
-    count: u32,
+struct WordSearch {
+    mat: Vec<Vec<char>>,
+    max: Point2D<usize>,
 }
 
-impl XmasCounter {
-    fn new() -> Self {
-        XmasCounter {
-            cache: ['.', '.', '.', '.'],
-            count: 0,
-        }
+impl WordSearch {
+    fn new(mat: Vec<Vec<char>>) -> Result<Self> {
+        let x_len = mat
+            .iter()
+            .map(|line| line.len())
+            .max()
+            .with_context(|| "Word search should have at least one line.")?;
+        let max = Point2D::new(x_len - 1, mat.len() - 1);
+        Ok(WordSearch { mat, max })
+    }
+
+    fn get(&self, pos: &Point2D<usize>) -> Option<char> {
+        self.mat
+            .get(pos.y)
+            .and_then(|line| line.get(pos.x))
+            .copied()
     }
 
-    fn reset_cache(&mut self) {
-        self.cache = ['.', '.', '.', '.'];
+    fn matches(&self, start: &Point2D<usize>, dir: Direction, word: &str) -> bool {
+        word.chars().enumerate().all(|(i, ch)| {
+            start
+                .step_within(dir, i, &self.max)
+                .and_then(|pos| self.get(&pos))
+                == Some(ch)
+        })
     }
 
-    fn process(&mut self, ch: char) {
-        // Do Shift
-        self.cache[0] = self.cache[1];
-        self.cache[1] = self.cache[2];
-        self.cache[2] = self.cache[3];
-        self.cache[3] = ch;
+    fn count_word(&self, word: &str) -> usize {
+        Point2D::new(0, 0)
+            .iter_to(&self.max)
+            .map(|start| {
+                Direction::ALL
+                    .iter()
+                    .filter(|dir| self.matches(&start, **dir, word))
+                    .count()
+            })
+            .sum()
+    }
 
-        // Examine for 'XMAS'
-        if (self.cache[0] == 'X'
-            && self.cache[1] == 'M'
-            && self.cache[2] == 'A'
-            && self.cache[3] == 'S')
-            || (self.cache[3] == 'X'
-                && self.cache[2] == 'M'
-                && self.cache[1] == 'A'
-                && self.cache[0] == 'S')
-        {
-            self.count += 1;
-        }
+    fn count_x_mas(&self) -> usize {
+        Point2D::new(0, 0)
+            .iter_to(&self.max)
+            .filter(|center| {
+                [Direction::NorthWest, Direction::NorthEast]
+                    .iter()
+                    .all(|dir| {
+                        center.step_within(*dir, 1, &self.max).is_some_and(|start| {
+                            let across = dir.turn(Turn::Around);
+                            self.matches(&start, across, "MAS")
+                                || self.matches(&start, across, "SAM")
+                        })
+                    })
+            })
+            .count()
     }
 }
 
@@ -49,101 +74,43 @@ pub struct Day04;
 impl Day for Day04 {
     fn main() -> Result<()> {
         let input = BufReader::new(get_input(2024, 04)?);
-        let mat: Vec<Vec<char>> = input
-            .lines()
-            .map(|line| line.unwrap().chars().collect())
-            .collect();
-        let y_len: usize = mat.len();
-        let x_len: usize = mat
-            .iter()
-            .map(|line| line.len())
-            .reduce(|max_len, len| if len >= max_len { len } else { max_len })
-            .with_context(|| "Unexpected failure in reduce.")?;
-        let mut counter = XmasCounter::new();
-        // East->West Check (+ West->East)
-        for y in 0..y_len {
-            for x in 0..x_len {
-                counter.process(mat[y][x]);
-            }
-            counter.reset_cache();
-        }
-        // North->South Check (+ South->North)
-        for x in 0..x_len {
-            for y in 0..y_len {
-                counter.process(mat[y][x]);
-            }
-            counter.reset_cache();
-        }
-        // ->South East Diagonal Check (+ ->North West)
-        for x_start in 0..x_len {
-            // Top
-            let mut x = x_start;
-            let mut y = 0;
-            while x < x_len && y < y_len {
-                counter.process(mat[y][x]);
-                x += 1;
-                y += 1;
-            }
-            counter.reset_cache();
-        }
-        for y_start in 1..y_len {
-            // Left-Side Skipping 0,0 common start
-            let mut y = y_start;
-            let mut x = 0;
-            while x < x_len && y < y_len {
-                counter.process(mat[y][x]);
-                x += 1;
-                y += 1;
-            }
-            counter.reset_cache();
-        }
-        // ->South West Diagonal Check (+ ->North East)
-        for x_start in 0..x_len {
-            // Top
-            let mut x = x_start;
-            let mut y = 0;
-            while y < y_len {
-                counter.process(mat[y][x]);
-                if x == 0 {
-                    break;
-                }
-                x -= 1;
-                y += 1;
-            }
-            counter.reset_cache();
-        }
-        for y_start in 1..y_len {
-            // Right-Side Skipping x_len-1,0 common start
-            let mut y = y_start;
-            let mut x = x_len - 1;
-            while y < y_len {
-                counter.process(mat[y][x]);
-                if x == 0 {
-                    break;
-                }
-                x -= 1;
-                y += 1;
-            }
-            counter.reset_cache();
-        }
-        println!("XMAS Instances: {}", counter.count);
-        let mut xmas_count = 0;
-        for x in 1..(x_len - 1) {
-            for y in 1..(y_len - 1) {
-                if mat[y][x] == 'A' {
-                    let nw = mat[y - 1][x - 1];
-                    let ne = mat[y - 1][x + 1];
-                    let sw = mat[y + 1][x - 1];
-                    let se = mat[y + 1][x + 1];
-                    let d1 = (nw == 'M' && se == 'S') || (nw == 'S' && se == 'M');
-                    let d2 = (ne == 'M' && sw == 'S') || (ne == 'S' && sw == 'M');
-                    if d1 && d2 {
-                        xmas_count += 1;
-                    }
-                }
-            }
-        }
-        println!("X-MAS Instances: {xmas_count}");
+        let search = WordSearch::new(
+            input
+                .lines()
+                .map(|line| line.unwrap().chars().collect())
+                .collect(),
+        )?;
+        println!("XMAS Instances: {}", search.count_word("XMAS"));
+        println!("X-MAS Instances: {}", search.count_x_mas());
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example_day_2024_04() {
+        let search = WordSearch::new(
+            [
+                "MMMSXXMASM",
+                "MSAMXMSMSA",
+                "AMXSXMAAMM",
+                "MSAMASMSMX",
+                "XMASAMXAMM",
+                "XXAMMXXAMA",
+                "SMSMSASXSS",
+                "SAXAMASAAA",
+                "MAMMMXMMMM",
+                "MXMXAXMASX",
+            ]
+            .iter()
+            .map(|line| line.chars().collect())
+            .collect(),
+        )
+        .unwrap();
+        assert_eq!(18, search.count_word("XMAS"));
+        assert_eq!(9, search.count_x_mas());
+    }
+}
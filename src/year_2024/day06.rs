@@ -43,17 +43,6 @@ impl GuardMap {
     }
 }
 
-impl Point2D<usize> {
-    fn step(&self, dir: Cardinal, mag: usize) -> Option<Self> {
-        match dir {
-            Cardinal::North => self.y.checked_sub(mag).map(|y| Point2D::new(self.x, y)),
-            Cardinal::East => self.x.checked_add(mag).map(|x| Point2D::new(x, self.y)),
-            Cardinal::South => self.y.checked_add(mag).map(|y| Point2D::new(self.x, y)),
-            Cardinal::West => self.x.checked_sub(mag).map(|x| Point2D::new(x, self.y)),
-        }
-    }
-}
-
 #[derive(Clone, Copy, Eq, Hash, PartialEq)]
 struct Guard {
     pos: Point2D<usize>,
@@ -69,11 +58,7 @@ impl Guard {
     }
 
     fn step(&mut self, map: &GuardMap) -> bool {
-        if let Some(next) = self
-            .pos
-            .step(self.dir, 1)
-            .filter(|next| next.y <= map.max.y && next.x <= map.max.x)
-        {
+        if let Some(next) = self.pos.step_within(self.dir, 1, &map.max) {
             match map.get(&next) {
                 Tile::Empty => {
                     self.pos = next;
@@ -135,8 +120,9 @@ impl Day for Day06 {
                     '.' => {
                         map.insert(point, Tile::Empty);
                     }
-                    '^' => {
+                    '^' | '>' | 'v' | '<' => {
                         guard.pos = point;
+                        guard.dir = Cardinal::try_from(ch)?;
                     }
                     _ => bail!("Unexpected char {ch}"),
                 }
@@ -151,12 +137,11 @@ impl Day for Day06 {
             let mut loop_count = 0;
             for v in visited {
                 // If the next step is a valid empty tile then we should try filling it with an obstruction
-                if let Some(next) = v.pos.step(v.dir, 1).filter(|n| {
-                    n.y <= map.max.y
-                        && n.x <= map.max.x
-                        && map.get(n) == Tile::Empty
-                        && !block_attempted.contains(n)
-                }) {
+                if let Some(next) = v
+                    .pos
+                    .step_within(v.dir, 1, &map.max)
+                    .filter(|n| map.get(n) == Tile::Empty && !block_attempted.contains(n))
+                {
                     block_attempted.insert(next);
                     let mut alt_map = map.clone();
                     alt_map.insert(next, Tile::Obstruction);
@@ -1,9 +1,11 @@
 use anyhow::{Context, Result};
 use lazy_regex::regex_captures;
-use std::cmp::{Ordering, PartialOrd};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
 use std::io::{BufRead, BufReader};
-use std::u64;
+use std::ops::Range;
 
+use crate::data::RangeSet;
 use crate::day::Day;
 use crate::util::input::get_input;
 
@@ -13,80 +15,68 @@ struct Point {
     y: u64,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
-struct Range {
-    low: u64,
-    high: u64,
+fn span(a: u64, b: u64) -> Range<u64> {
+    a.min(b)..a.max(b) + 1
 }
 
-impl Range {
-    fn new(a: u64, b: u64) -> Self {
-        if a < b {
-            Range { low: a, high: b }
-        } else {
-            Range { low: b, high: a }
-        }
-    }
-
-    fn size(&self) -> u64 {
-        (self.high - self.low) + 1
-    }
-
-    fn overlaps(&self, other: &Range) -> bool {
-        self.low.max(other.low) <= self.high.min(other.high)
-    }
-
-    fn inner(&self) -> Option<Self> {
-        if self.size() < 3 {
-            // Squished out of existence
-            None
-        } else {
-            Some(Range {
-                low: self.low + 1,
-                high: self.high - 1,
-            })
-        }
+fn inner(range: &Range<u64>) -> Option<Range<u64>> {
+    if range.end - range.start < 3 {
+        // Squished out of existence
+        None
+    } else {
+        Some(range.start + 1..range.end - 1)
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Ord, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 struct Rectangle {
-    x: Range,
-    y: Range,
+    x: Range<u64>,
+    y: Range<u64>,
 }
 
 impl Rectangle {
     fn new(a: &Point, b: &Point) -> Self {
         Rectangle {
-            x: Range::new(a.x, b.x),
-            y: Range::new(a.y, b.y),
+            x: span(a.x, b.x),
+            y: span(a.y, b.y),
         }
     }
 
     fn area(&self) -> u64 {
-        self.x.size() * self.y.size()
-    }
-
-    fn overlaps(&self, other: &Rectangle) -> bool {
-        self.x.overlaps(&other.x) && self.y.overlaps(&other.y)
+        (self.x.end - self.x.start) * (self.y.end - self.y.start)
     }
 
     fn inner(&self) -> Option<Self> {
-        self.x
-            .inner()
-            .zip(self.y.inner())
+        inner(&self.x)
+            .zip(inner(&self.y))
             .map(|(x, y)| Rectangle { x, y })
     }
 }
 
-impl PartialOrd for Rectangle {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(
-            self.area()
-                .cmp(&other.area())
-                .then(self.x.cmp(&other.x))
-                .then(self.y.cmp(&other.y)),
-        )
+// Outline segments grouped by the column (or row) they sit on
+#[derive(Default)]
+struct Outline {
+    columns: BTreeMap<u64, RangeSet<u64>>,
+    rows: BTreeMap<u64, RangeSet<u64>>,
+}
+
+impl Outline {
+    fn push(&mut self, a: &Point, b: &Point) {
+        if a.x == b.x {
+            self.columns.entry(a.x).or_default().insert(span(a.y, b.y));
+        } else {
+            self.rows.entry(a.y).or_default().insert(span(a.x, b.x));
+        }
+    }
+
+    fn overlaps(&self, rect: &Rectangle) -> bool {
+        self.columns
+            .range(rect.x.clone())
+            .any(|(_, ys)| ys.overlaps(&rect.y))
+            || self
+                .rows
+                .range(rect.y.clone())
+                .any(|(_, xs)| xs.overlaps(&rect.x))
     }
 }
 
@@ -98,7 +88,7 @@ impl Day for Day09 {
 
         // Parse Points & Measure Basic Areas
         let mut red_tiles: Vec<Point> = Vec::new();
-        let mut outline: Vec<Rectangle> = Vec::new();
+        let mut outline = Outline::default();
         let mut red_zones: Vec<Rectangle> = Vec::new();
         for (line_num, line) in input.lines().map(|l| l.unwrap()).enumerate() {
             let (_, x_str, y_str) = regex_captures!(r"([0-9]+),([0-9]+)", &line)
@@ -113,19 +103,18 @@ impl Day for Day09 {
             }
             // Draw outline
             if let Some(prev) = red_tiles.last() {
-                outline.push(Rectangle::new(prev, &new_tile));
+                outline.push(prev, &new_tile);
             }
             red_tiles.push(new_tile);
         }
 
         // Complete outline
         if let Some((first, last)) = red_tiles.first().zip(red_tiles.last()) {
-            outline.push(Rectangle::new(last, first));
+            outline.push(last, first);
         }
 
-        // Sort red cornered rectangles
-        red_zones.sort_unstable();
-        red_zones.reverse();
+        // Sort red cornered rectangles, largest first
+        red_zones.sort_unstable_by_key(|zone| Reverse(zone.area()));
 
         println!(
             "Largest area based on red tiles, {}",
@@ -134,7 +123,7 @@ impl Day for Day09 {
 
         for red_zone in &red_zones {
             if let Some(inner) = red_zone.inner() {
-                if !outline.iter().any(|line| inner.overlaps(line)) {
+                if !outline.overlaps(&inner) {
                     println!("Christmas area, {}", red_zone.area());
                     break;
                 }